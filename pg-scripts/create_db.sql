@@ -2,15 +2,21 @@ CREATE TABLE feed_val (
   id uuid PRIMARY KEY,
   title varchar(1024) UNIQUE NOT NULL,
   synced boolean NOT NULL,
-  update_ts timestamp with time zone NOT NULL
+  update_ts timestamp with time zone NOT NULL,
+  change_xid xid8 NOT NULL DEFAULT pg_current_xact_id()
 );
 
+CREATE INDEX feed_val_change_xid ON feed_val (change_xid);
+
 CREATE TABLE feed_url (
   id uuid PRIMARY KEY,
   url varchar(1024) UNIQUE NOT NULL,
-  feed_id uuid REFERENCES feed_val (id) NOT NULL,
+  feed_id uuid REFERENCES feed_val (id) ON DELETE CASCADE NOT NULL,
   manual boolean NOT NULL,
   status SMALLINT,
+  preferred boolean NOT NULL DEFAULT false,
+  superseded_by uuid REFERENCES feed_url (id) ON DELETE SET NULL,
+  error varchar(1024),
   synced boolean NOT NULL,
   update_ts timestamp with time zone NOT NULL
 );
@@ -20,46 +26,150 @@ CREATE TABLE channel_val (
   title varchar(512) UNIQUE NOT NULL,
   description varchar(2048) NOT NULL,
   image varchar(1024),
-  feed_id uuid REFERENCES feed_val (id) NOT NULL,
-  update_ts timestamp with time zone NOT NULL
+  feed_id uuid REFERENCES feed_val (id) ON DELETE CASCADE NOT NULL,
+  author text,
+  language text,
+  categories text[] NOT NULL DEFAULT '{}',
+  link text,
+  copyright text,
+  owner_name text,
+  owner_email text,
+  show_type varchar(16),
+  update_ts timestamp with time zone NOT NULL,
+  change_xid xid8 NOT NULL DEFAULT pg_current_xact_id()
 );
 
+CREATE INDEX channel_val_change_xid ON channel_val (change_xid);
+
 CREATE TABLE channel_meta (
-  user_id varchar(512),
+  user_id varchar(512) NOT NULL,
   id uuid PRIMARY KEY,
-  channel_id uuid REFERENCES channel_val (id) NOT NULL,
+  channel_id uuid REFERENCES channel_val (id) ON DELETE CASCADE NOT NULL,
   active boolean NOT NULL,
   synced boolean NOT NULL,
   volume float NOT NULL,
   playback_rate float NOT NULL,
-  update_ts timestamp with time zone NOT NULL
+  update_ts timestamp with time zone NOT NULL,
+  change_xid xid8 NOT NULL DEFAULT pg_current_xact_id(),
+  UNIQUE (user_id, channel_id)
 );
 
+CREATE INDEX channel_meta_change_xid ON channel_meta (change_xid);
+
 CREATE TABLE item_val (
   id uuid PRIMARY KEY,
   title varchar(512) NOT NULL,
   date timestamp with time zone NOT NULL,
   enclosure_type varchar(128) NOT NULL,
   enclosure_url varchar(1024) NOT NULL,
-  channel_id uuid REFERENCES channel_val (id) NOT NULL,
+  canonical_enclosure_url varchar(1024),
+  channel_id uuid REFERENCES channel_val (id) ON DELETE CASCADE NOT NULL,
   size BIGINT NOT NULL,
+  image varchar(1024),
+  date_estimated boolean NOT NULL DEFAULT false,
+  enclosure_missing boolean NOT NULL DEFAULT false,
+  probed_size BIGINT,
+  probed_type text,
+  probed_url text,
+  probe_ts timestamp with time zone,
+  duration BIGINT,
+  duration_probe_ts timestamp with time zone,
+  update_ts timestamp with time zone NOT NULL,
+  change_xid xid8 NOT NULL DEFAULT pg_current_xact_id()
+);
+
+CREATE INDEX item_val_change_xid ON item_val (change_xid);
+
+CREATE TABLE item_enclosure (
+  id uuid PRIMARY KEY,
+  item_id uuid REFERENCES item_val (id) ON DELETE CASCADE NOT NULL,
+  url varchar(1024) NOT NULL,
+  canonical_url varchar(1024),
+  mime_type varchar(128) NOT NULL,
+  length BIGINT NOT NULL,
+  bitrate BIGINT,
+  title varchar(512),
+  main boolean NOT NULL,
   update_ts timestamp with time zone NOT NULL
 );
 
+CREATE INDEX item_enclosure_item_id ON item_enclosure (item_id);
+
 CREATE TYPE download_status AS ENUM ('NotRequested', 'Pending', 'InProgress', 'Ok', 'Error');
 
 CREATE TABLE item_meta (
-  user_id varchar(512),
+  user_id varchar(512) NOT NULL,
   id uuid PRIMARY KEY,
-  item_id uuid REFERENCES item_val (id) NOT NULL,
+  item_id uuid REFERENCES item_val (id) ON DELETE CASCADE NOT NULL,
   new boolean NOT NULL,
   download_status download_status NOT NULL,
   playback_time float,
   play_count int NOT NULL,
   synced boolean NOT NULL,
+  update_ts timestamp with time zone NOT NULL,
+  change_xid xid8 NOT NULL DEFAULT pg_current_xact_id(),
+  UNIQUE (user_id, item_id)
+);
+
+CREATE INDEX item_meta_change_xid ON item_meta (change_xid);
+
+CREATE TABLE feed_fetch_log (
+  id uuid PRIMARY KEY,
+  feed_id uuid REFERENCES feed_val (id) ON DELETE CASCADE NOT NULL,
+  url varchar(1024),
+  status SMALLINT,
+  error text,
+  channel_count int NOT NULL,
+  item_count int NOT NULL,
+  duration_ms BIGINT NOT NULL,
+  fetch_ts timestamp with time zone NOT NULL
+);
+
+CREATE INDEX feed_fetch_log_feed_id_fetch_ts ON feed_fetch_log (feed_id, fetch_ts);
+
+CREATE TABLE feed_fetch_diagnostic (
+  fetch_id uuid REFERENCES feed_fetch_log (id) ON DELETE CASCADE NOT NULL,
+  position int NOT NULL,
+  path varchar(1024) NOT NULL,
+  severity varchar(16) NOT NULL,
+  message text NOT NULL,
+  PRIMARY KEY (fetch_id, position)
+);
+
+CREATE TABLE feed_schedule (
+  feed_id uuid PRIMARY KEY REFERENCES feed_val (id) ON DELETE CASCADE,
+  consecutive_failures int NOT NULL,
+  next_fetch_ts timestamp with time zone,
+  disabled boolean NOT NULL,
+  update_ts timestamp with time zone NOT NULL
+);
+
+CREATE TABLE tombstone (
+  id uuid PRIMARY KEY,
+  object_type varchar(32) NOT NULL,
+  delete_ts timestamp with time zone NOT NULL,
+  change_xid xid8 NOT NULL DEFAULT pg_current_xact_id()
+);
+
+CREATE INDEX tombstone_object_type_delete_ts ON tombstone (object_type, delete_ts);
+CREATE INDEX tombstone_change_xid ON tombstone (change_xid);
+
+CREATE TABLE playlist (
+  id uuid PRIMARY KEY,
+  user_id varchar(512) NOT NULL,
+  title varchar(512) NOT NULL,
   update_ts timestamp with time zone NOT NULL
 );
 
+CREATE INDEX playlist_user_id ON playlist (user_id);
+
+CREATE TABLE playlist_item (
+  playlist_id uuid REFERENCES playlist (id) ON DELETE CASCADE NOT NULL,
+  item_id uuid REFERENCES item_val (id) ON DELETE CASCADE NOT NULL,
+  position int NOT NULL,
+  PRIMARY KEY (playlist_id, item_id)
+);
+
 CREATE FUNCTION set_update_timestamp() RETURNS trigger AS $$
 BEGIN
   new.update_ts := current_timestamp;
@@ -67,6 +177,45 @@ BEGIN
 END;
 $$ LANGUAGE plpgsql;
 
+-- the transaction changing a row last, on which sync tokens are based
+CREATE FUNCTION set_change_xid() RETURNS trigger AS $$
+BEGIN
+  new.change_xid := pg_current_xact_id();
+  RETURN new;
+END;
+$$ LANGUAGE plpgsql;
+
+-- recording when an enclosure was probed alone is no change of an item
+CREATE FUNCTION item_changed(item_val, item_val) RETURNS boolean AS $$
+  SELECT to_jsonb($1) - '{update_ts,change_xid,probe_ts,duration_probe_ts}'::text[] IS DISTINCT FROM to_jsonb($2) - '{update_ts,change_xid,probe_ts,duration_probe_ts}'::text[];
+$$ LANGUAGE sql IMMUTABLE;
+
+CREATE TRIGGER change_xid_feed
+BEFORE UPDATE ON feed_val
+FOR EACH ROW 
+EXECUTE PROCEDURE set_change_xid();
+
+CREATE TRIGGER change_xid_channel
+BEFORE UPDATE ON channel_val
+FOR EACH ROW 
+EXECUTE PROCEDURE set_change_xid();
+
+CREATE TRIGGER change_xid_item
+BEFORE UPDATE ON item_val
+FOR EACH ROW 
+WHEN (item_changed(old, new))
+EXECUTE PROCEDURE set_change_xid();
+
+CREATE TRIGGER change_xid_channel_meta
+BEFORE UPDATE ON channel_meta
+FOR EACH ROW 
+EXECUTE PROCEDURE set_change_xid();
+
+CREATE TRIGGER change_xid_item_meta
+BEFORE UPDATE ON item_meta
+FOR EACH ROW 
+EXECUTE PROCEDURE set_change_xid();
+
 CREATE TRIGGER insert_timestamp_item
 BEFORE INSERT ON item_val
 FOR EACH ROW 
@@ -75,6 +224,7 @@ EXECUTE PROCEDURE set_update_timestamp();
 CREATE TRIGGER update_timestamp_item
 BEFORE UPDATE ON item_val
 FOR EACH ROW 
+WHEN (item_changed(old, new))
 EXECUTE PROCEDURE set_update_timestamp();
 
 CREATE TRIGGER insert_timestamp_channel
@@ -87,18 +237,92 @@ BEFORE UPDATE ON channel_val
 FOR EACH ROW 
 EXECUTE PROCEDURE set_update_timestamp();
 
+CREATE FUNCTION notify_change() RETURNS trigger AS $$
+BEGIN
+  PERFORM pg_notify('changes', json_build_object('type', TG_ARGV[0], 'op', lower(TG_OP), 'id', new.id, 'update_ts', new.update_ts)::text);
+  RETURN new;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_change_channel
+AFTER INSERT OR UPDATE ON channel_val
+FOR EACH ROW 
+EXECUTE PROCEDURE notify_change('channel');
+
+CREATE TRIGGER notify_insert_item
+AFTER INSERT ON item_val
+FOR EACH ROW 
+EXECUTE PROCEDURE notify_change('item');
+
+CREATE TRIGGER notify_update_item
+AFTER UPDATE ON item_val
+FOR EACH ROW 
+WHEN (item_changed(old, new))
+EXECUTE PROCEDURE notify_change('item');
+
+CREATE FUNCTION notify_meta_change() RETURNS trigger AS $$
+BEGIN
+  PERFORM pg_notify('changes', json_build_object('type', TG_ARGV[0], 'op', lower(TG_OP), 'id', new.id, 'update_ts', new.update_ts, 'user_id', new.user_id)::text);
+  RETURN new;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_change_channel_meta
+AFTER INSERT OR UPDATE ON channel_meta
+FOR EACH ROW 
+EXECUTE PROCEDURE notify_meta_change('channel_meta');
+
+CREATE TRIGGER notify_change_item_meta
+AFTER INSERT OR UPDATE ON item_meta
+FOR EACH ROW 
+EXECUTE PROCEDURE notify_meta_change('item_meta');
+
+CREATE FUNCTION record_tombstone() RETURNS trigger AS $$
+BEGIN
+  INSERT INTO tombstone (id, object_type, delete_ts) VALUES (old.id, TG_ARGV[0], current_timestamp)
+    ON CONFLICT (id) DO UPDATE SET object_type = excluded.object_type, delete_ts = excluded.delete_ts, change_xid = pg_current_xact_id();
+  RETURN old;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER;
+
+CREATE TRIGGER record_tombstone_feed
+AFTER DELETE ON feed_val
+FOR EACH ROW 
+EXECUTE PROCEDURE record_tombstone('feed');
+
+CREATE TRIGGER record_tombstone_channel
+AFTER DELETE ON channel_val
+FOR EACH ROW 
+EXECUTE PROCEDURE record_tombstone('channel');
+
+CREATE TRIGGER record_tombstone_item
+AFTER DELETE ON item_val
+FOR EACH ROW 
+EXECUTE PROCEDURE record_tombstone('item');
+
 CREATE ROLE api_updater LOGIN PASSWORD '{{updater_password}}';
 
 GRANT SELECT, INSERT, UPDATE ON feed_val TO api_updater;
 GRANT SELECT, INSERT, UPDATE ON feed_url TO api_updater;
 GRANT SELECT, INSERT, UPDATE ON channel_val TO api_updater;
 GRANT SELECT, INSERT, UPDATE ON item_val TO api_updater;
+GRANT SELECT, INSERT, DELETE ON item_enclosure TO api_updater;
+GRANT SELECT, INSERT, DELETE ON feed_fetch_log TO api_updater;
+GRANT SELECT, INSERT ON feed_fetch_diagnostic TO api_updater;
+GRANT SELECT, INSERT, UPDATE ON feed_schedule TO api_updater;
 
 CREATE ROLE api_service LOGIN PASSWORD '{{service_password}}';
 
 GRANT SELECT ON channel_val TO api_service;
 GRANT SELECT ON item_val TO api_service;
+GRANT SELECT ON item_enclosure TO api_service;
 GRANT SELECT ON feed_val TO api_service;
 GRANT SELECT ON feed_url TO api_service;
+GRANT SELECT ON feed_fetch_log TO api_service;
+GRANT SELECT ON feed_fetch_diagnostic TO api_service;
+GRANT SELECT ON tombstone TO api_service;
+GRANT SELECT, INSERT, UPDATE ON feed_schedule TO api_service;
 GRANT SELECT, INSERT, UPDATE ON channel_meta TO api_service;
 GRANT SELECT, INSERT, UPDATE ON item_meta TO api_service;
+GRANT SELECT, INSERT, UPDATE, DELETE ON playlist TO api_service;
+GRANT SELECT, INSERT, DELETE ON playlist_item TO api_service;
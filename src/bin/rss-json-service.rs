@@ -1,57 +1,494 @@
 extern crate podcast_player_api;
 use hyper::service::{make_service_fn, service_fn};
-use hyper::{Body, Method, Request, Response, Server, StatusCode};
-use podcast_player_api::{fetcher, repo::Repo, updater::Updater};
-use podcast_player_common::{channel_val::ChannelVal, item_val::ItemVal, FeedVal};
-use serde::Deserialize;
+use hyper::{
+    header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+    Body, HeaderMap, Method, Request, Response, Server, StatusCode,
+};
+use podcast_player_api::{
+    canonical_url::{CanonicalUrlConfig, UrlCanonicalizer},
+    change_events::ChangeNotifier,
+    duration_probe::{DurationProbeConfig, DurationProber},
+    enclosure_probe::{EnclosureProbeConfig, EnclosureProber},
+    feed_health::{FeedDiagnostics, FeedHealth, HEALTH_HISTORY_LENGTH},
+    feed_schedule::{BackoffConfig, FeedSchedule},
+    fetcher::{Fetcher, FetcherConfig},
+    image_cache::{ImageCache, ImageCacheConfig},
+    item::select_enclosure,
+    playlist::{
+        CreatePlaylistRequest, Playlist, PlaylistDetails, PlaylistItemsRequest, PlaylistUpdate,
+    },
+    repo::Repo,
+    rss_feed::ParseOptions,
+    sync::SyncRequest,
+    sync_token::{ChangeFilter, SyncToken},
+    tombstone::{tombstones_requested, Changes},
+    updater::Updater,
+    validation::Validation,
+};
+use podcast_player_common::{channel_val::ChannelVal, FeedVal};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::{env, str};
-use tokio::{fs, spawn, time::Duration};
+use tokio::{fs, spawn};
 use url::Url;
+use uuid::Uuid;
 
 #[derive(Debug, Deserialize)]
 pub struct PodcastPlayerApiConfig {
     pub api_connection: String,
     pub updater_connection: String,
+    #[serde(default)]
+    pub backoff: BackoffConfig,
+    #[serde(default)]
+    pub fetcher: FetcherConfig,
+    #[serde(default)]
+    pub parser: ParseOptions,
+    #[serde(default)]
+    pub image_cache: ImageCacheConfig,
+    #[serde(default)]
+    pub canonical_url: CanonicalUrlConfig,
+    #[serde(default)]
+    pub enclosure_probe: EnclosureProbeConfig,
+    #[serde(default)]
+    pub duration_probe: DurationProbeConfig,
 }
 
-const TIMEOUT: Duration = Duration::from_secs(3);
+const SYNC_TOKEN_HEADER: &str = "sync-token";
+// set by the authenticating reverse proxy
+const USER_ID_HEADER: &str = "x-user-id";
 
-async fn router(req: Request<Body>, repo: Repo) -> Result<Response<Body>, anyhow::Error> {
-    let path = req.uri().path().split("/").collect::<Vec<&str>>();
-    let query = Url::parse(&format!("http://dummy.com{}", req.uri().to_string()))?
+async fn router(
+    req: Request<Body>,
+    repo: Repo,
+    fetcher: Fetcher,
+    parser: ParseOptions,
+    image_cache: ImageCache,
+    notifier: ChangeNotifier,
+) -> Result<Response<Body>, anyhow::Error> {
+    let (parts, body) = req.into_parts();
+    let path = parts.uri.path().split("/").collect::<Vec<&str>>();
+    let query = Url::parse(&format!("http://dummy.com{}", parts.uri.to_string()))?
         .query_pairs()
         .into_owned()
         .collect::<HashMap<String, String>>();
-    let since = query.get("since").and_then(|s| Some(s.as_str()));
-
-    match (req.method(), &path[1..]) {
-        (&Method::GET, &["feeds"]) => Ok(Response::new(Body::from(serde_json::to_string(
-            &repo.get_objects::<FeedVal>(since).await?,
-        )?))),
-        (&Method::GET, &["channels"]) => Ok(Response::new(Body::from(serde_json::to_string(
-            &repo.get_objects::<ChannelVal>(since).await?,
-        )?))),
-        (&Method::GET, &["items"]) => Ok(Response::new(Body::from(serde_json::to_string(
-            &repo.get_objects::<ItemVal>(since).await?,
-        )?))),
+
+    match (&parts.method, &path[1..]) {
+        (&Method::GET, &["feeds"]) => {
+            let filter = match change_filter(&query) {
+                Ok(filter) => filter,
+                Err(_) => return Ok(bad_request()),
+            };
+            let token = repo.get_sync_token().await?;
+
+            changes_response(
+                &repo,
+                "feed",
+                repo.get_objects::<FeedVal>(&filter).await?,
+                &filter,
+                token,
+                &query,
+            )
+            .await
+        }
+        (&Method::GET, &["feeds", id, "health"]) => {
+            let feed_id: Uuid = match id.parse() {
+                Ok(id) => id,
+                Err(_) => return Ok(bad_request()),
+            };
+            let fetches = repo
+                .get_feed_fetch_logs_by_feed_id(&feed_id, HEALTH_HISTORY_LENGTH)
+                .await?;
+
+            Ok(Response::new(Body::from(serde_json::to_string(
+                &FeedHealth::from_logs(&feed_id, fetches),
+            )?)))
+        }
+        (&Method::GET, &["admin", "feeds", id, "diagnostics"]) => {
+            let feed_id: Uuid = match id.parse() {
+                Ok(id) => id,
+                Err(_) => return Ok(bad_request()),
+            };
+            let fetch = repo
+                .get_feed_fetch_logs_by_feed_id(&feed_id, 1)
+                .await?
+                .pop();
+            let diagnostics = match &fetch {
+                Some(f) => repo.get_feed_fetch_diagnostics_by_fetch_id(&f.id).await?,
+                None => Vec::new(),
+            };
+
+            Ok(Response::new(Body::from(serde_json::to_string(
+                &FeedDiagnostics {
+                    feed_id,
+                    fetch,
+                    diagnostics,
+                },
+            )?)))
+        }
+        (&Method::POST, &["admin", "feeds", id, "enable"]) => {
+            let feed_id: Uuid = match id.parse() {
+                Ok(id) => id,
+                Err(_) => return Ok(bad_request()),
+            };
+            let mut schedule = repo
+                .get_feed_schedule_by_feed_id(&feed_id)
+                .await?
+                .unwrap_or_else(|| FeedSchedule::new(&feed_id));
+
+            schedule.enable();
+
+            Ok(Response::new(Body::from(serde_json::to_string(
+                &repo.upsert_feed_schedule(&schedule).await?,
+            )?)))
+        }
+        (&Method::GET, &["events"]) => {
+            let user_id = match user_id(&parts.headers) {
+                Some(user_id) => user_id,
+                None => return Ok(unauthorized()),
+            };
+            let types = query
+                .get("types")
+                .map(|t| t.split(',').map(String::from).collect::<Vec<String>>());
+
+            Ok(Response::builder()
+                .header(CONTENT_TYPE, "text/event-stream")
+                .header(CACHE_CONTROL, "no-cache")
+                .body(notifier.event_stream(types, user_id))?)
+        }
+        (&Method::GET, &["channels"]) => {
+            let filter = match change_filter(&query) {
+                Ok(filter) => filter,
+                Err(_) => return Ok(bad_request()),
+            };
+            let token = repo.get_sync_token().await?;
+
+            changes_response(
+                &repo,
+                "channel",
+                repo.get_channels(&filter).await?,
+                &filter,
+                token,
+                &query,
+            )
+            .await
+        }
+        (&Method::GET, &["channels", id, "image"]) => {
+            let (id, size) = match (
+                id.parse::<Uuid>(),
+                query.get("size").map(|s| s.parse::<u32>()).transpose(),
+            ) {
+                (Ok(id), Ok(size)) if image_cache.is_valid_size(size) => (id, size),
+                _ => return Ok(bad_request()),
+            };
+            let url = match repo.get_channel_by_id(&id).await? {
+                Some(ChannelVal {
+                    image: Some(url), ..
+                }) => url,
+                _ => return Ok(not_found()),
+            };
+            let image = image_cache.get(&fetcher, &url, size).await?;
+            let etag = format!("\"{}\"", image.etag);
+            let builder = Response::builder().header(ETAG, &etag).header(
+                CACHE_CONTROL,
+                format!("public, max-age={}", image_cache.max_age().as_secs()),
+            );
+
+            if parts.headers.get(IF_NONE_MATCH).map(|v| v.as_bytes()) == Some(etag.as_bytes()) {
+                return Ok(builder
+                    .status(StatusCode::NOT_MODIFIED)
+                    .body(Body::empty())?);
+            }
+
+            Ok(builder
+                .header(CONTENT_TYPE, image.content_type)
+                .body(Body::from(image.data))?)
+        }
+        (&Method::GET, &["items"]) => {
+            let filter = match change_filter(&query) {
+                Ok(filter) => filter,
+                Err(_) => return Ok(bad_request()),
+            };
+            let token = repo.get_sync_token().await?;
+
+            changes_response(
+                &repo,
+                "item",
+                repo.get_items(&filter).await?,
+                &filter,
+                token,
+                &query,
+            )
+            .await
+        }
+        (&Method::GET, &["items", id, "enclosures"]) => {
+            let item_id: Uuid = match id.parse() {
+                Ok(id) => id,
+                Err(_) => return Ok(bad_request()),
+            };
+
+            Ok(Response::new(Body::from(serde_json::to_string(
+                &repo.get_item_enclosures_by_item_id(&item_id).await?,
+            )?)))
+        }
         (&Method::GET | &Method::HEAD, &["items", id, "stream"]) => {
-            let item = repo.get_item_by_id(&id.parse()?).await?;
+            let item_id: Uuid = match id.parse() {
+                Ok(id) => id,
+                Err(_) => return Ok(bad_request()),
+            };
+            let item = repo.get_item_by_id(&item_id).await?;
+            let mime_type = query.get("type").map(|t| t.as_str());
+            let quality = query.get("quality").map(|q| q.as_str());
+            let canonical = query.get("canonical").is_some_and(|c| c == "true");
+            let url = match (mime_type, quality) {
+                (None, None) if canonical => Some(
+                    repo.get_item_canonical_enclosure_url(&item.id)
+                        .await?
+                        .unwrap_or(item.enclosure_url),
+                ),
+                (None, None) => Some(item.enclosure_url),
+                _ => {
+                    let enclosures = repo.get_item_enclosures_by_item_id(&item.id).await?;
+
+                    match select_enclosure(&enclosures, mime_type, quality) {
+                        Ok(enclosure) => enclosure.map(|e| String::from(e.stream_url(canonical))),
+                        Err(_) => return Ok(bad_request()),
+                    }
+                }
+            };
+
+            match url {
+                Some(url) => fetcher
+                    .request(&*url, &parts.method)
+                    .await?
+                    .0
+                    .ok_or(anyhow::anyhow!("error requesting enclosure")),
+                None => Ok(not_found()),
+            }
+        }
+        (&Method::POST, &["sync"]) => {
+            let user_id = match user_id(&parts.headers) {
+                Some(user_id) => user_id,
+                None => return Ok(unauthorized()),
+            };
+            let request: SyncRequest = match read_json(&fetcher, &parts.headers, body).await? {
+                Some(request) => request,
+                None => return Ok(bad_request()),
+            };
+
+            Ok(Response::new(Body::from(serde_json::to_string(
+                &repo.sync_meta(user_id, &request).await?,
+            )?)))
+        }
+        (_, &["playlists", ..]) => {
+            playlist_router(
+                &parts.method,
+                &path[1..],
+                &parts.headers,
+                body,
+                &repo,
+                &fetcher,
+            )
+            .await
+        }
+        (&Method::POST, &["admin", "validate"]) => {
+            let validation = match query.get("url") {
+                Some(url) => Validation::from_url(&fetcher, url, &parser).await,
+                None => {
+                    let content_type = parts
+                        .headers
+                        .get(CONTENT_TYPE)
+                        .and_then(|c| c.to_str().ok());
+                    let body = fetcher.read_message_body(&parts.headers, body).await?;
+
+                    Validation::from_body(&body, content_type, &parser)
+                }
+            };
+            let mut res = Response::new(Body::from(serde_json::to_string(&validation)?));
+
+            if !validation.valid {
+                *res.status_mut() = StatusCode::UNPROCESSABLE_ENTITY;
+            }
+
+            Ok(res)
+        }
+        _ => Ok(not_found()),
+    }
+}
+
+/// Lists the objects as is or, if requested, together with the tombstones of deleted objects.
+///
+/// The token for the next request is returned in a header and, with tombstones, in the body.
+async fn changes_response<T: Serialize>(
+    repo: &Repo,
+    object_type: &str,
+    objects: Vec<T>,
+    filter: &ChangeFilter,
+    token: SyncToken,
+    query: &HashMap<String, String>,
+) -> Result<Response<Body>, anyhow::Error> {
+    let body = match tombstones_requested(query) {
+        true => serde_json::to_string(&Changes {
+            token: token.to_string(),
+            objects,
+            tombstones: repo.get_tombstones(object_type, filter).await?,
+        })?,
+        false => serde_json::to_string(&objects)?,
+    };
 
-            fetcher::request(&*item.enclosure_url, &TIMEOUT, &req.method())
+    Ok(Response::builder()
+        .header(SYNC_TOKEN_HEADER, token.to_string())
+        .body(Body::from(body))?)
+}
+
+async fn playlist_router(
+    method: &Method,
+    path: &[&str],
+    headers: &HeaderMap,
+    body: Body,
+    repo: &Repo,
+    fetcher: &Fetcher,
+) -> Result<Response<Body>, anyhow::Error> {
+    let user_id = match user_id(headers) {
+        Some(user_id) => user_id,
+        None => return Ok(unauthorized()),
+    };
+    // the path is "playlists/<playlist id>/items/<item id>" or a prefix of it
+    let (id, item_id) = match (
+        path.get(1).map(|id| id.parse::<Uuid>()).transpose(),
+        path.get(3).map(|id| id.parse::<Uuid>()).transpose(),
+    ) {
+        (Ok(id), Ok(item_id)) => (id, item_id),
+        _ => return Ok(bad_request()),
+    };
+
+    let update = match (method, path, id, item_id) {
+        (&Method::GET, &["playlists"], _, _) => {
+            return Ok(Response::new(Body::from(serde_json::to_string(
+                &repo.get_playlists_by_user_id(user_id).await?,
+            )?)))
+        }
+        (&Method::POST, &["playlists"], _, _) => {
+            let request: CreatePlaylistRequest = match read_json(fetcher, headers, body).await? {
+                Some(request) => request,
+                None => return Ok(bad_request()),
+            };
+
+            PlaylistUpdate::Updated(
+                repo.create_playlist(&Playlist::new(user_id, &request.title))
+                    .await?,
+            )
+        }
+        (&Method::GET, &["playlists", _], Some(id), _) => {
+            let playlist = match repo.get_playlist_by_id(user_id, &id).await? {
+                Some(playlist) => playlist,
+                None => return Ok(not_found()),
+            };
+
+            return Ok(Response::new(Body::from(serde_json::to_string(
+                &PlaylistDetails {
+                    items: repo.get_playlist_items(&playlist.id).await?,
+                    playlist,
+                },
+            )?)));
+        }
+        (&Method::DELETE, &["playlists", _], Some(id), _) => {
+            return Ok(match repo.delete_playlist(user_id, &id).await? {
+                true => Response::default(),
+                false => not_found(),
+            });
+        }
+        (&Method::POST | &Method::PUT, &["playlists", _, "items"], Some(id), _) => {
+            let request: PlaylistItemsRequest = match read_json(fetcher, headers, body).await? {
+                Some(request) => request,
+                None => return Ok(bad_request()),
+            };
+            let update = match method {
+                &Method::POST => {
+                    repo.update_playlist(user_id, &id, |p| {
+                        p.append(&request.item_ids);
+                        true
+                    })
+                    .await?
+                }
+                _ => {
+                    repo.update_playlist(user_id, &id, |p| p.reorder(&request.item_ids).is_ok())
+                        .await?
+                }
+            };
+
+            match update {
+                // unknown items or a new order with different items
+                PlaylistUpdate::Rejected => return Ok(unprocessable_entity()),
+                update => update,
+            }
+        }
+        (&Method::DELETE, &["playlists", _, "items", _], Some(id), Some(item_id)) => {
+            // rejected, if the item is not in the playlist
+            repo.update_playlist(user_id, &id, |p| p.remove(&item_id))
                 .await?
-                .0
-                .ok_or(anyhow::anyhow!("error requesting enclosure"))
         }
-        _ => {
-            let mut not_found = Response::default();
-            *not_found.status_mut() = StatusCode::NOT_FOUND;
-            Ok(not_found)
+        _ => PlaylistUpdate::NotFound,
+    };
+
+    match update {
+        PlaylistUpdate::Updated(playlist) => {
+            Ok(Response::new(Body::from(serde_json::to_string(&playlist)?)))
         }
+        PlaylistUpdate::Rejected | PlaylistUpdate::NotFound => Ok(not_found()),
     }
 }
 
+/// Reads a JSON request body; `None` if it is malformed.
+async fn read_json<T: DeserializeOwned>(
+    fetcher: &Fetcher,
+    headers: &HeaderMap,
+    body: Body,
+) -> Result<Option<T>, anyhow::Error> {
+    let body = fetcher.read_message_body(headers, body).await?;
+
+    Ok(serde_json::from_slice(&body).ok())
+}
+
+/// The id of the user, who was authenticated by the reverse proxy.
+fn user_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(USER_ID_HEADER)
+        .and_then(|u| u.to_str().ok())
+        .filter(|u| !u.is_empty())
+}
+
+fn unauthorized() -> Response<Body> {
+    let mut unauthorized = Response::default();
+    *unauthorized.status_mut() = StatusCode::UNAUTHORIZED;
+    unauthorized
+}
+
+fn change_filter(query: &HashMap<String, String>) -> Result<ChangeFilter, anyhow::Error> {
+    ChangeFilter::from_query(
+        query.get("since").map(|s| s.as_str()),
+        query.get("token").map(|t| t.as_str()),
+    )
+}
+
+fn bad_request() -> Response<Body> {
+    let mut bad_request = Response::default();
+    *bad_request.status_mut() = StatusCode::BAD_REQUEST;
+    bad_request
+}
+
+fn unprocessable_entity() -> Response<Body> {
+    let mut unprocessable_entity = Response::default();
+    *unprocessable_entity.status_mut() = StatusCode::UNPROCESSABLE_ENTITY;
+    unprocessable_entity
+}
+
+fn not_found() -> Response<Body> {
+    let mut not_found = Response::default();
+    *not_found.status_mut() = StatusCode::NOT_FOUND;
+    not_found
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     env_logger::init();
@@ -65,10 +502,47 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     let repo = Repo::new(&config.api_connection).await?;
 
-    let updater = Updater::new(&config.updater_connection);
+    let fetcher = Fetcher::new(&config.fetcher)?;
+    let image_cache = ImageCache::new(&config.image_cache);
+    let notifier = ChangeNotifier::new();
+
+    let updater = Updater::new(
+        &config.updater_connection,
+        config.backoff.clone(),
+        fetcher.clone(),
+        config.parser,
+        UrlCanonicalizer::new(&config.canonical_url),
+    );
 
     spawn(async move { updater.update_loop().await });
 
+    let listener = notifier.clone();
+    let listen_connection = config.api_connection.clone();
+
+    spawn(async move { listener.listen_loop(&listen_connection).await });
+
+    let prober = EnclosureProber::new(
+        &config.updater_connection,
+        fetcher.clone(),
+        config.enclosure_probe.clone(),
+    );
+
+    spawn(async move { prober.probe_loop().await });
+
+    let cache = image_cache.clone();
+
+    spawn(async move { cache.cleanup_loop().await });
+
+    if config.duration_probe.enabled {
+        let duration_prober = DurationProber::new(
+            &config.updater_connection,
+            fetcher.clone(),
+            config.duration_probe.clone(),
+        );
+
+        spawn(async move { duration_prober.probe_loop().await });
+    }
+
     let addr: SocketAddr = match env::var("HYPER_BIND_ADDRESS") {
         Ok(s) => s,
         Err(_) => String::from("127.0.0.1:8000"),
@@ -77,7 +551,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     .unwrap();
     let service = make_service_fn(|_| {
         let repo = repo.clone();
-        async { Ok::<_, anyhow::Error>(service_fn(move |req| router(req, repo.to_owned()))) }
+        let fetcher = fetcher.clone();
+        let parser = config.parser;
+        let image_cache = image_cache.clone();
+        let notifier = notifier.clone();
+        async move {
+            Ok::<_, anyhow::Error>(service_fn(move |req| {
+                router(
+                    req,
+                    repo.to_owned(),
+                    fetcher.to_owned(),
+                    parser,
+                    image_cache.to_owned(),
+                    notifier.to_owned(),
+                )
+            }))
+        }
     });
     let server = Server::bind(&addr).serve(service);
 
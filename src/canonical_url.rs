@@ -0,0 +1,136 @@
+use serde::Deserialize;
+use url::Url;
+
+#[cfg(test)]
+mod test;
+
+// tracking prefixes may be nested, e.g. podtrac wrapping chartable
+const MAX_NESTED_PREFIXES: usize = 10;
+
+/// Prefixes of analytics redirects; "*" matches a single path segment.
+pub const DEFAULT_TRACKING_PREFIXES: &[&str] = &[
+    "dts.podtrac.com/redirect.*/",
+    "www.podtrac.com/pts/redirect.*/",
+    "podtrac.com/pts/redirect.*/",
+    "chtbl.com/track/*/",
+    "chrt.fm/track/*/",
+    "pdst.fm/e/",
+    "op3.dev/e/",
+    "op3.dev/e,*/",
+    "pfx.vpixl.com/*/",
+    "mgln.ai/e/*/",
+    "arttrk.com/p/*/",
+    "verifi.podscribe.com/rss/p/",
+    "pscrb.fm/rss/p/",
+    "prfx.byspotify.com/e/",
+    "tracking.swap.fm/track/*/",
+    "claritaspod.com/measure/",
+    "media.blubrry.com/*/",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CanonicalUrlConfig {
+    pub tracking_prefixes: Vec<String>,
+}
+
+impl Default for CanonicalUrlConfig {
+    fn default() -> Self {
+        Self {
+            tracking_prefixes: DEFAULT_TRACKING_PREFIXES
+                .iter()
+                .map(|p| String::from(*p))
+                .collect(),
+        }
+    }
+}
+
+/// Removes tracking prefixes from enclosure urls.
+#[derive(Debug, Clone)]
+pub struct UrlCanonicalizer {
+    tracking_prefixes: Vec<String>,
+}
+
+impl UrlCanonicalizer {
+    pub fn new(config: &CanonicalUrlConfig) -> Self {
+        Self {
+            tracking_prefixes: config.tracking_prefixes.clone(),
+        }
+    }
+
+    /// Returns the url without tracking prefixes or `None`, if there are none.
+    pub fn canonicalize(&self, url: &str) -> Option<String> {
+        let mut current = String::from(url);
+
+        for _ in 0..MAX_NESTED_PREFIXES {
+            let (scheme, rest) = match current.split_once("://") {
+                Some((scheme, rest)) if scheme == "http" || scheme == "https" => (scheme, rest),
+                _ => break,
+            };
+            let target = match self
+                .tracking_prefixes
+                .iter()
+                .find_map(|p| strip_pattern(p, rest))
+            {
+                Some(target) if !target.is_empty() => target,
+                _ => break,
+            };
+
+            let candidate = match target.starts_with("http://") || target.starts_with("https://") {
+                true => String::from(target),
+                // the scheme of the target is assumed to be the one of the prefix
+                false => format!("{}://{}", scheme, target),
+            };
+
+            // files hosted on a tracking host leave no url behind the prefix
+            if !is_target_url(&candidate) {
+                break;
+            }
+
+            current = candidate;
+        }
+
+        match current == url {
+            true => None,
+            false => Some(current),
+        }
+    }
+}
+
+/// Checks whether the rest of a stripped url is a url of a file on another host.
+fn is_target_url(url: &str) -> bool {
+    // the host is taken as written, as a parsed numeric segment like "2023" becomes an ip address
+    let host = url
+        .split_once("://")
+        .and_then(|(_, rest)| rest.split(['/', '?', '#']).next())
+        .unwrap_or_default();
+
+    host.contains('.') && Url::parse(url).is_ok_and(|u| u.path() != "/")
+}
+
+/// Strips a prefix pattern from a url without scheme and returns the rest.
+fn strip_pattern<'a>(pattern: &str, url: &'a str) -> Option<&'a str> {
+    let mut parts = pattern.split('*');
+    let mut rest = strip_prefix_ignore_case(url, parts.next()?)?;
+
+    for part in parts {
+        // the wildcard matches at least one character, but no slash
+        let segment_end = rest.find('/').unwrap_or(rest.len());
+        let start = rest.char_indices().nth(1).map_or(rest.len(), |(i, _)| i);
+        let position = rest[start..]
+            .find(part)
+            .map(|p| p + start)
+            .filter(|p| *p <= segment_end)?;
+
+        rest = &rest[position + part.len()..];
+    }
+
+    Some(rest)
+}
+
+fn strip_prefix_ignore_case<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    match value.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => Some(&value[prefix.len()..]),
+        _ => None,
+    }
+}
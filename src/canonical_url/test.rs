@@ -0,0 +1,93 @@
+use super::{CanonicalUrlConfig, UrlCanonicalizer};
+
+fn canonicalize(url: &str) -> Option<String> {
+    UrlCanonicalizer::new(&CanonicalUrlConfig::default()).canonicalize(url)
+}
+
+#[test]
+fn podtrac() {
+    assert_eq!(
+        canonicalize("http://dts.podtrac.com/redirect.mp3/cdn.oreillystatic.com/radar/bot-podcast/Chris_Messina_on_Facebook_as_a_utility.mp3").as_deref(),
+        Some("http://cdn.oreillystatic.com/radar/bot-podcast/Chris_Messina_on_Facebook_as_a_utility.mp3")
+    );
+    assert_eq!(
+        canonicalize("https://www.podtrac.com/pts/redirect.m4a/example.com/episode.m4a").as_deref(),
+        Some("https://example.com/episode.m4a")
+    );
+}
+
+#[test]
+fn chartable_and_op3() {
+    assert_eq!(
+        canonicalize("https://chtbl.com/track/12345/traffic.megaphone.fm/ABC123.mp3?updated=1")
+            .as_deref(),
+        Some("https://traffic.megaphone.fm/ABC123.mp3?updated=1")
+    );
+    assert_eq!(
+        canonicalize("https://op3.dev/e/https://example.com/episode.mp3").as_deref(),
+        Some("https://example.com/episode.mp3")
+    );
+    assert_eq!(
+        canonicalize("https://op3.dev/e,pg=123/example.com/episode.mp3").as_deref(),
+        Some("https://example.com/episode.mp3")
+    );
+}
+
+#[test]
+fn nested_prefixes() {
+    assert_eq!(
+        canonicalize("https://dts.podtrac.com/redirect.mp3/chtbl.com/track/ABC/pdst.fm/e/http://example.com/episode.mp3").as_deref(),
+        Some("http://example.com/episode.mp3")
+    );
+}
+
+#[test]
+fn untracked_urls() {
+    assert_eq!(canonicalize("https://example.com/episode.mp3"), None);
+    assert_eq!(canonicalize("https://chtbl.com/track/"), None);
+    assert_eq!(canonicalize("https://chtbl.com/track/a/b"), None);
+    assert_eq!(
+        canonicalize("https://example.com/chtbl.com/track/a/b.mp3"),
+        None
+    );
+    assert_eq!(
+        canonicalize("ftp://dts.podtrac.com/redirect.mp3/example.com/e.mp3"),
+        None
+    );
+}
+
+#[test]
+fn files_on_tracking_hosts() {
+    assert_eq!(
+        canonicalize("https://media.blubrry.com/show/episode.mp3"),
+        None
+    );
+    assert_eq!(
+        canonicalize("https://media.blubrry.com/show/2023/episode.mp3"),
+        None
+    );
+    assert_eq!(canonicalize("https://pfx.vpixl.com/abc/episode.mp3"), None);
+    assert_eq!(
+        canonicalize("https://media.blubrry.com/show/content.blubrry.com/show/episode.mp3")
+            .as_deref(),
+        Some("https://content.blubrry.com/show/episode.mp3")
+    );
+}
+
+#[test]
+fn configured_prefixes() {
+    let canonicalizer = UrlCanonicalizer::new(&CanonicalUrlConfig {
+        tracking_prefixes: vec![String::from("stats.example.net/*/*/")],
+    });
+
+    assert_eq!(
+        canonicalizer
+            .canonicalize("https://stats.example.net/show/42/media.example.com/e.mp3")
+            .as_deref(),
+        Some("https://media.example.com/e.mp3")
+    );
+    assert_eq!(
+        canonicalizer.canonicalize("https://chtbl.com/track/1/example.com/e.mp3"),
+        None
+    );
+}
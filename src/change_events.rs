@@ -0,0 +1,174 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset};
+use hyper::Body;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::future::poll_fn;
+use tokio::{
+    select, spawn,
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc,
+    },
+    time::{interval, sleep, Duration},
+};
+use tokio_postgres::{AsyncMessage, NoTls};
+use uuid::Uuid;
+
+#[cfg(test)]
+mod test;
+
+/// The channel the triggers of the schema notify.
+const NOTIFICATION_CHANNEL: &str = "changes";
+const EVENT_BUFFER_SIZE: usize = 256;
+// comments sent to idle clients, so that disconnected ones are noticed and proxies keep the
+// connection open
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    /// "channel", "item", "channel_meta", or "item_meta"
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// "insert" or "update"
+    pub op: String,
+    pub id: Uuid,
+    pub update_ts: DateTime<FixedOffset>,
+    /// the user of metadata events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+impl ChangeEvent {
+    pub fn from_notification(payload: &str) -> Result<Self> {
+        Ok(serde_json::from_str(payload)?)
+    }
+
+    /// Formats the event as a server-sent event named after its type.
+    pub fn to_sse(&self) -> Result<String> {
+        Ok(format!(
+            "event: {}\ndata: {}\n\n",
+            self.kind,
+            serde_json::to_string(self)?
+        ))
+    }
+}
+
+/// Distributes the change notifications of the database to the connected clients.
+#[derive(Debug, Clone)]
+pub struct ChangeNotifier {
+    sender: broadcast::Sender<ChangeEvent>,
+}
+
+impl Default for ChangeNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChangeNotifier {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER_SIZE);
+
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: ChangeEvent) {
+        // sending only fails if no client is connected
+        let _ = self.sender.send(event);
+    }
+
+    pub async fn listen_loop(&self, connection: &str) {
+        loop {
+            if let Err(e) = self.listen(connection).await {
+                error!("error listening for changes; reconnecting in 10s: {}", e);
+            }
+
+            sleep(Duration::from_secs(10)).await;
+        }
+    }
+
+    async fn listen(&self, config: &str) -> Result<()> {
+        let (client, mut connection) = tokio_postgres::connect(config, NoTls).await?;
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        // notifications are only delivered while the connection is polled
+        let connection_task = spawn(async move {
+            while let Some(message) = poll_fn(|cx| connection.poll_message(cx)).await {
+                if let AsyncMessage::Notification(notification) = message? {
+                    if sender.send(notification).is_err() {
+                        break;
+                    }
+                }
+            }
+
+            Ok::<_, tokio_postgres::Error>(())
+        });
+
+        client
+            .batch_execute(&format!("LISTEN {}", NOTIFICATION_CHANNEL))
+            .await?;
+        info!("listening for changes");
+
+        while let Some(notification) = receiver.recv().await {
+            match ChangeEvent::from_notification(notification.payload()) {
+                Ok(event) => self.publish(event),
+                Err(e) => warn!(
+                    "ignoring malformed notification \"{}\": {}",
+                    notification.payload(),
+                    e
+                ),
+            }
+        }
+
+        connection_task.await??;
+
+        Err(anyhow!("connection closed"))
+    }
+
+    /// Streams the events of the given types (all if none are given) as server-sent events;
+    /// metadata events are restricted to the ones of the user.
+    ///
+    /// Clients, which fell behind, receive a "lagged" event with the number of missed events
+    /// and should catch up by polling with `since`.
+    pub fn event_stream(&self, types: Option<Vec<String>>, user_id: &str) -> Body {
+        let user_id = String::from(user_id);
+        let mut events = self.sender.subscribe();
+        let (mut sender, body) = Body::channel();
+
+        spawn(async move {
+            let mut keepalive = interval(KEEPALIVE_INTERVAL);
+
+            loop {
+                let message = select! {
+                    event = events.recv() => match event {
+                        Ok(event)
+                            if types.as_ref().is_none_or(|t| t.contains(&event.kind))
+                                && event.user_id.as_ref().is_none_or(|u| *u == user_id) =>
+                        {
+                            match event.to_sse() {
+                                Ok(message) => message,
+                                Err(e) => {
+                                    warn!("error formatting event: {}", e);
+                                    continue;
+                                }
+                            }
+                        }
+                        Ok(_) => continue,
+                        Err(RecvError::Lagged(missed)) => {
+                            format!("event: lagged\ndata: {}\n\n", missed)
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = keepalive.tick() => String::from(": keepalive\n\n"),
+                };
+
+                // the client disconnected
+                if sender.send_data(message.into()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        body
+    }
+}
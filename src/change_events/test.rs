@@ -0,0 +1,91 @@
+use super::{ChangeEvent, ChangeNotifier};
+use chrono::DateTime;
+use hyper::body::HttpBody;
+use std::str::FromStr;
+use uuid::Uuid;
+
+fn event(kind: &str) -> ChangeEvent {
+    ChangeEvent {
+        kind: String::from(kind),
+        op: String::from("insert"),
+        id: Uuid::from_str("1f8f16fb-4a44-4d46-8de2-7b84e0b5c4a6").unwrap(),
+        update_ts: DateTime::parse_from_rfc3339("2022-03-01T10:15:00.123456+00:00").unwrap(),
+        user_id: None,
+    }
+}
+
+fn meta_event(user_id: &str) -> ChangeEvent {
+    ChangeEvent {
+        kind: String::from("item_meta"),
+        user_id: Some(String::from(user_id)),
+        ..event("item_meta")
+    }
+}
+
+#[test]
+fn event_from_notification() {
+    // the payload as built by the notify_change trigger function
+    let payload = r#"{"type" : "item", "op" : "insert", "id" : "1f8f16fb-4a44-4d46-8de2-7b84e0b5c4a6", "update_ts" : "2022-03-01T10:15:00.123456+00:00"}"#;
+
+    assert_eq!(
+        ChangeEvent::from_notification(payload).unwrap(),
+        event("item")
+    );
+    assert!(ChangeEvent::from_notification("{}").is_err());
+
+    // the payload as built by the notify_meta_change trigger function
+    let payload = r#"{"type" : "item_meta", "op" : "insert", "id" : "1f8f16fb-4a44-4d46-8de2-7b84e0b5c4a6", "update_ts" : "2022-03-01T10:15:00.123456+00:00", "user_id" : "alice"}"#;
+
+    assert_eq!(
+        ChangeEvent::from_notification(payload).unwrap(),
+        meta_event("alice")
+    );
+}
+
+#[test]
+fn event_to_sse() {
+    let sse = event("channel").to_sse().unwrap();
+    let data = sse
+        .strip_prefix("event: channel\ndata: ")
+        .and_then(|s| s.strip_suffix("\n\n"))
+        .unwrap();
+
+    assert!(!data.contains('\n'));
+    assert_eq!(
+        ChangeEvent::from_notification(data).unwrap(),
+        event("channel")
+    );
+}
+
+#[tokio::test]
+async fn event_stream_filters_types() {
+    let notifier = ChangeNotifier::new();
+    let mut body = notifier.event_stream(Some(vec![String::from("item")]), "alice");
+
+    // the first keepalive is sent right away
+    assert_eq!(body.data().await.unwrap().unwrap(), ": keepalive\n\n");
+
+    notifier.publish(event("channel"));
+    notifier.publish(event("item"));
+
+    assert_eq!(
+        body.data().await.unwrap().unwrap(),
+        event("item").to_sse().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn event_stream_filters_users() {
+    let notifier = ChangeNotifier::new();
+    let mut body = notifier.event_stream(None, "alice");
+
+    assert_eq!(body.data().await.unwrap().unwrap(), ": keepalive\n\n");
+
+    notifier.publish(meta_event("bob"));
+    notifier.publish(meta_event("alice"));
+
+    assert_eq!(
+        body.data().await.unwrap().unwrap(),
+        meta_event("alice").to_sse().unwrap()
+    );
+}
@@ -0,0 +1,33 @@
+use crate::rss_feed::RssChannelMetadata;
+use podcast_player_common::channel_val::ChannelVal;
+use serde::Serialize;
+use std::convert::TryFrom;
+
+/// A channel together with the metadata not part of `ChannelVal`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Channel {
+    #[serde(flatten)]
+    pub val: ChannelVal,
+    #[serde(flatten)]
+    pub metadata: RssChannelMetadata,
+}
+
+impl TryFrom<&tokio_postgres::Row> for Channel {
+    type Error = anyhow::Error;
+
+    fn try_from(row: &tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            val: ChannelVal::try_from(row)?,
+            metadata: RssChannelMetadata {
+                author: row.try_get("author")?,
+                language: row.try_get("language")?,
+                categories: row.try_get("categories")?,
+                link: row.try_get("link")?,
+                copyright: row.try_get("copyright")?,
+                owner_name: row.try_get("owner_name")?,
+                owner_email: row.try_get("owner_email")?,
+                show_type: row.try_get("show_type")?,
+            },
+        })
+    }
+}
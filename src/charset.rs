@@ -0,0 +1,92 @@
+use anyhow::{anyhow, Result};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+#[cfg(test)]
+mod test;
+
+/// Decodes a feed body into a string.
+///
+/// The encoding is taken from the byte order mark, the charset of the content type, or the
+/// XML declaration (in this order); UTF-8 is assumed if none of them is available.
+pub fn decode_feed(body: &[u8], content_type: Option<&str>) -> Result<String> {
+    let charset_encoding = content_type
+        .and_then(content_type_charset)
+        .and_then(|charset| match Encoding::for_label(charset.as_bytes()) {
+            Some(encoding) => Some(encoding),
+            None => {
+                log::warn!("ignoring unknown charset \"{}\" in content type", charset);
+                None
+            }
+        });
+    let encoding = match charset_encoding {
+        Some(encoding) => encoding,
+        None => xml_declaration_encoding(body)?.unwrap_or(UTF_8),
+    };
+    // decode performs byte order mark sniffing, which takes precedence over the given encoding
+    let (text, used_encoding, had_errors) = encoding.decode(body);
+
+    if had_errors {
+        log::warn!(
+            "feed contained byte sequences invalid in {}",
+            used_encoding.name()
+        );
+    }
+
+    Ok(text.into_owned())
+}
+
+fn content_type_charset(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+
+        Some(String::from(value.trim().trim_matches('"').trim()))
+    })
+}
+
+fn xml_declaration_encoding(body: &[u8]) -> Result<Option<&'static Encoding>> {
+    // UTF-16 without byte order mark can only be recognized by the pattern of "<?"
+    if body.starts_with(&[0x3C, 0x00, 0x3F, 0x00]) {
+        return Ok(Some(UTF_16LE));
+    }
+
+    if body.starts_with(&[0x00, 0x3C, 0x00, 0x3F]) {
+        return Ok(Some(UTF_16BE));
+    }
+
+    let body = body.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(body);
+
+    if !body.starts_with(b"<?xml") {
+        return Ok(None);
+    }
+
+    let declaration = match body.windows(2).position(|w| w == b"?>") {
+        Some(end) => String::from_utf8_lossy(&body[..end]),
+        None => return Ok(None),
+    };
+    let label = match declaration.split_once("encoding") {
+        Some((_, rest)) => rest
+            .trim_start()
+            .strip_prefix('=')
+            .map(|rest| rest.trim_start())
+            .and_then(|rest| {
+                let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+
+                rest[1..].split(quote).next()
+            }),
+        None => return Ok(None),
+    };
+
+    match label.map(|label| (label, Encoding::for_label(label.trim().as_bytes()))) {
+        // the declaration was readable as ASCII, so it cannot actually be UTF-16
+        Some((_, Some(encoding))) if encoding == UTF_16LE || encoding == UTF_16BE => {
+            Ok(Some(UTF_8))
+        }
+        Some((_, Some(encoding))) => Ok(Some(encoding)),
+        Some((label, None)) => Err(anyhow!("unknown encoding \"{}\" in xml declaration", label)),
+        None => Err(anyhow!("malformed encoding in xml declaration")),
+    }
+}
@@ -0,0 +1,81 @@
+use super::decode_feed;
+use crate::rss_feed::RssFeed;
+use std::{convert::TryFrom, fs};
+
+fn parse(file: &str, content_type: Option<&str>) -> RssFeed {
+    RssFeed::try_from(&*decode_feed(&fs::read(file).unwrap(), content_type).unwrap()).unwrap()
+}
+
+#[test]
+fn decode_utf_8() {
+    let feed = parse(
+        "testFiles/bots.xml",
+        Some("application/rss+xml; charset=UTF-8"),
+    );
+
+    assert_eq!(
+        feed.channels[0].title,
+        "O'Reilly Bots Podcast - O'Reilly Media Podcast"
+    );
+}
+
+#[test]
+fn decode_iso_8859_1() {
+    let feed = parse("testFiles/encoding_iso-8859-1.xml", Some("application/xml"));
+
+    assert_eq!(feed.channels[0].title, "Grüße aus Köln");
+    assert_eq!(feed.channels[0].description, "Ein Podcast über Käse");
+    assert_eq!(
+        feed.channels[0].items[0].title,
+        "Folge 1: Größe ist relativ"
+    );
+}
+
+#[test]
+fn decode_windows_1252() {
+    let feed = parse("testFiles/encoding_windows-1252.xml", None);
+
+    assert_eq!(feed.channels[0].title, "Smart “quotes” – €");
+    assert_eq!(feed.channels[0].description, "Bullets • and dashes —");
+    assert_eq!(feed.channels[0].items[0].title, "Episode 1: It’s here");
+}
+
+#[test]
+fn decode_utf_16() {
+    for file in [
+        "testFiles/encoding_utf-16le.xml",
+        "testFiles/encoding_utf-16be.xml",
+    ] {
+        let feed = parse(file, None);
+
+        assert_eq!(feed.channels[0].title, "Grüße aus Köln");
+        assert_eq!(
+            feed.channels[0].items[0].title,
+            "Folge 1: Größe ist relativ"
+        );
+    }
+}
+
+#[test]
+fn decode_content_type_charset() {
+    let feed = parse(
+        "testFiles/encoding_undeclared.xml",
+        Some("text/xml; charset=\"ISO-8859-1\""),
+    );
+
+    assert_eq!(feed.channels[0].title, "Grüße aus Köln");
+
+    let feed = parse("testFiles/encoding_undeclared.xml", Some("text/xml"));
+
+    assert_ne!(feed.channels[0].title, "Grüße aus Köln");
+}
+
+#[test]
+fn decode_byte_order_mark_precedence() {
+    let feed = parse(
+        "testFiles/encoding_utf-16le.xml",
+        Some("text/xml; charset=ISO-8859-1"),
+    );
+
+    assert_eq!(feed.channels[0].title, "Grüße aus Köln");
+}
@@ -0,0 +1,152 @@
+use crate::{fetcher::Fetcher, repo::Repo};
+use anyhow::{anyhow, Result};
+use hyper::{
+    header::{HeaderMap, CONTENT_RANGE},
+    StatusCode,
+};
+use log::{error, info, trace, warn};
+use serde::Deserialize;
+use tokio::time::{sleep, Duration};
+
+mod media;
+
+pub use media::{probe_media, MediaProbe};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DurationProbeConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// maximum number of enclosures probed per run
+    pub batch_size: i64,
+    /// number of bytes requested at once
+    pub window_size: u64,
+    /// maximum number of range requests per enclosure
+    pub max_requests: usize,
+}
+
+impl Default for DurationProbeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 10 * 60,
+            batch_size: 50,
+            window_size: 64 * 1024,
+            max_requests: 4,
+        }
+    }
+}
+
+/// Background job deriving the duration of enclosures from their first bytes.
+pub struct DurationProber {
+    connection: String,
+    fetcher: Fetcher,
+    config: DurationProbeConfig,
+}
+
+impl DurationProber {
+    pub fn new(connection: &str, fetcher: Fetcher, config: DurationProbeConfig) -> Self {
+        Self {
+            connection: connection.into(),
+            fetcher,
+            config,
+        }
+    }
+
+    pub async fn probe_loop(&self) {
+        loop {
+            if let Err(e) = &self.probe_durations().await {
+                error!("error probing durations: {}", e);
+            }
+
+            sleep(Duration::from_secs(self.config.interval_secs)).await;
+        }
+    }
+
+    async fn probe_durations(&self) -> Result<()> {
+        trace!("probing durations");
+
+        let repo = Repo::new(&self.connection).await?;
+        let items = repo
+            .get_items_without_duration(self.config.batch_size)
+            .await?;
+
+        trace!("got {} enclosures to probe", items.len());
+
+        for item in items {
+            let size = u64::try_from(item.size).ok().filter(|s| *s > 0);
+            // failed probes are recorded as well, so that they are not retried on every run
+            let duration = match self.probe_duration(&item.enclosure_url, size).await {
+                Ok(duration) => duration,
+                Err(e) => {
+                    warn!("error probing \"{}\": {}", item.enclosure_url, e);
+                    None
+                }
+            };
+
+            if let Err(e) = repo
+                .update_item_duration(&item.id, duration.map(|d| d.round() as i64))
+                .await
+            {
+                error!(
+                    "error recording duration of \"{}\": {}",
+                    item.enclosure_url, e
+                );
+            }
+        }
+
+        info!("probed durations");
+
+        Ok(())
+    }
+
+    async fn probe_duration(&self, url: &str, size: Option<u64>) -> Result<Option<f64>> {
+        let mut offset = 0;
+        let mut total_size = size;
+
+        for _ in 0..self.config.max_requests {
+            let res = self
+                .fetcher
+                .request_range(url, offset, self.config.window_size)
+                .await?
+                .0
+                .ok_or(anyhow!("error requesting enclosure"))?;
+
+            if res.status() == StatusCode::PARTIAL_CONTENT {
+                total_size = content_range_total(res.headers()).or(total_size);
+            } else if offset > 0 {
+                // the server sent the file from its start
+                return Err(anyhow!("range requests not supported"));
+            }
+
+            let data = self
+                .fetcher
+                .read_body_prefix(res, self.config.window_size as usize)
+                .await?;
+
+            match probe_media(&data, offset, total_size) {
+                MediaProbe::Duration(duration) => return Ok(Some(duration)),
+                MediaProbe::NeedBytes(next) => offset = next,
+                MediaProbe::Unknown => return Ok(None),
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// The complete length of a file given in a content range header ("bytes 0-99/1234").
+fn content_range_total(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit_once('/')?
+        .1
+        .trim()
+        .parse()
+        .ok()
+}
@@ -0,0 +1,323 @@
+//! Derivation of the duration of media files from a window of their bytes.
+//!
+//! MP3 files are recognized by their first frame; the number of frames is taken from its Xing
+//! (or Info) or VBRI header, otherwise the stream is taken to have a constant bitrate. MP4 files
+//! are walked box by box until the mvhd box inside the moov box is found.
+
+const MP4_BOX_TYPES: &[&[u8; 4]] = &[
+    b"ftyp", b"styp", b"moov", b"mdat", b"free", b"skip", b"wide", b"pdin", b"uuid", b"moof",
+    b"mfra", b"meta", b"sidx",
+];
+
+// bitrates in kbit/s by bitrate index; 0 marks the free and the invalid index
+const BITRATES_MPEG1_LAYER1: [u32; 16] = [
+    0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448, 0,
+];
+const BITRATES_MPEG1_LAYER2: [u32; 16] = [
+    0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 0,
+];
+const BITRATES_MPEG1_LAYER3: [u32; 16] = [
+    0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 0,
+];
+const BITRATES_MPEG2_LAYER1: [u32; 16] = [
+    0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256, 0,
+];
+const BITRATES_MPEG2_LAYER23: [u32; 16] = [
+    0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160, 0,
+];
+
+const SAMPLE_RATES_MPEG1: [u32; 3] = [44100, 48000, 32000];
+
+#[derive(Debug, PartialEq)]
+pub enum MediaProbe {
+    /// duration in seconds
+    Duration(f64),
+    /// the duration can only be derived from the bytes starting at the given offset
+    NeedBytes(u64),
+    Unknown,
+}
+
+/// Probes `data`, which starts at `offset` of a file of `total_size` bytes.
+pub fn probe_media(data: &[u8], offset: u64, total_size: Option<u64>) -> MediaProbe {
+    if is_mp4_box(data) {
+        probe_mp4(data, offset)
+    } else {
+        probe_mp3(data, offset, total_size)
+    }
+}
+
+fn is_mp4_box(data: &[u8]) -> bool {
+    data.len() >= 8 && MP4_BOX_TYPES.iter().any(|t| data[4..8] == t[..])
+}
+
+/// Returns the type, the header length, and the total size (0 meaning up to the end of the
+/// file) of the box at the start of `data`.
+fn box_header(data: &[u8]) -> Option<(&[u8], u64, u64)> {
+    if data.len() < 8 {
+        return None;
+    }
+
+    let (header_length, size) = match read_u32(data, 0)? {
+        1 => (16, read_u64(data, 8)?),
+        size => (8, size as u64),
+    };
+
+    if size != 0 && size < header_length {
+        return None;
+    }
+
+    Some((&data[4..8], header_length, size))
+}
+
+fn probe_mp4(data: &[u8], offset: u64) -> MediaProbe {
+    let mut pos = 0u64;
+
+    while pos < data.len() as u64 {
+        let (box_type, header_length, size) = match box_header(&data[pos as usize..]) {
+            Some(header) => header,
+            None => break,
+        };
+
+        if box_type == b"moov" {
+            let end = match size {
+                0 => data.len() as u64,
+                size => pos.saturating_add(size).min(data.len() as u64),
+            };
+
+            return match mvhd_duration(&data[(pos + header_length).min(end) as usize..end as usize])
+            {
+                Some(duration) => MediaProbe::Duration(duration),
+                // the window did not cover the mvhd box; retry with the moov box at its start
+                None if pos > 0 => MediaProbe::NeedBytes(offset + pos),
+                None => MediaProbe::Unknown,
+            };
+        }
+
+        // sizes are read from the file and may be arbitrarily large
+        pos = match pos.checked_add(size) {
+            Some(next) if next > pos => next,
+            _ => return MediaProbe::Unknown,
+        };
+    }
+
+    match offset.checked_add(pos) {
+        Some(_) if pos == 0 => MediaProbe::Unknown,
+        Some(next) => MediaProbe::NeedBytes(next),
+        None => MediaProbe::Unknown,
+    }
+}
+
+fn mvhd_duration(moov: &[u8]) -> Option<f64> {
+    let mut pos = 0usize;
+
+    while let Some((box_type, header_length, size)) = box_header(&moov[pos..]) {
+        if box_type == b"mvhd" {
+            let content = &moov[pos + header_length as usize..];
+            let (timescale, duration) = match content.first()? {
+                0 => (read_u32(content, 12)?, read_u32(content, 16)? as u64),
+                1 => (read_u32(content, 20)?, read_u64(content, 24)?),
+                _ => return None,
+            };
+
+            // a duration of all ones means the duration is unknown
+            if timescale == 0
+                || duration == 0
+                || duration == u32::MAX as u64
+                || duration == u64::MAX
+            {
+                return None;
+            }
+
+            return Some(duration as f64 / timescale as f64);
+        }
+
+        if size == 0 {
+            return None;
+        }
+
+        pos = pos.checked_add(usize::try_from(size).ok()?)?;
+
+        if pos >= moov.len() {
+            return None;
+        }
+    }
+
+    None
+}
+
+#[derive(Debug, PartialEq)]
+struct FrameHeader {
+    mpeg1: bool,
+    /// 0 for MPEG 2.5, 2 for MPEG 2, and 3 for MPEG 1
+    version: u8,
+    /// 1 for layer III, 2 for layer II, and 3 for layer I
+    layer: u8,
+    /// in kbit/s
+    bitrate: u32,
+    sample_rate: u32,
+    padding: bool,
+    mono: bool,
+}
+
+impl FrameHeader {
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < 4 || data[0] != 0xFF || data[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+
+        let version = (data[1] >> 3) & 3;
+        let layer = (data[1] >> 1) & 3;
+        let bitrate_index = (data[2] >> 4) as usize;
+        let sample_rate_index = ((data[2] >> 2) & 3) as usize;
+
+        if version == 1 || layer == 0 || sample_rate_index == 3 {
+            return None;
+        }
+
+        let mpeg1 = version == 3;
+        let bitrates = match (mpeg1, layer) {
+            (true, 3) => &BITRATES_MPEG1_LAYER1,
+            (true, 2) => &BITRATES_MPEG1_LAYER2,
+            (true, _) => &BITRATES_MPEG1_LAYER3,
+            (false, 3) => &BITRATES_MPEG2_LAYER1,
+            (false, _) => &BITRATES_MPEG2_LAYER23,
+        };
+        let bitrate = bitrates[bitrate_index];
+
+        if bitrate == 0 {
+            return None;
+        }
+
+        Some(Self {
+            mpeg1,
+            version,
+            layer,
+            bitrate,
+            sample_rate: SAMPLE_RATES_MPEG1[sample_rate_index]
+                >> match version {
+                    3 => 0,
+                    2 => 1,
+                    _ => 2,
+                },
+            padding: (data[2] >> 1) & 1 == 1,
+            mono: data[3] >> 6 == 3,
+        })
+    }
+
+    fn samples_per_frame(&self) -> u32 {
+        match (self.layer, self.mpeg1) {
+            (3, _) => 384,
+            (2, _) | (1, true) => 1152,
+            _ => 576,
+        }
+    }
+
+    fn frame_length(&self) -> usize {
+        let slot_size = if self.layer == 3 { 4 } else { 1 };
+        let slots =
+            self.samples_per_frame() / 8 / slot_size * self.bitrate * 1000 / self.sample_rate;
+
+        ((slots + self.padding as u32) * slot_size) as usize
+    }
+
+    /// Offset of a Xing header, which follows the side information of layer III frames.
+    fn xing_offset(&self) -> usize {
+        4 + match (self.mpeg1, self.mono) {
+            (true, false) => 32,
+            (true, true) | (false, false) => 17,
+            (false, true) => 9,
+        }
+    }
+
+    fn continues_with(&self, other: &Self) -> bool {
+        self.version == other.version
+            && self.layer == other.layer
+            && self.sample_rate == other.sample_rate
+    }
+}
+
+fn probe_mp3(data: &[u8], offset: u64, total_size: Option<u64>) -> MediaProbe {
+    if data.len() >= 10 && data.starts_with(b"ID3") {
+        let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+        let audio_start = 10 + synchsafe(&data[6..10]) + footer;
+
+        if audio_start + 4 > data.len() as u64 {
+            return MediaProbe::NeedBytes(offset + audio_start);
+        }
+
+        return probe_mp3_frames(
+            &data[audio_start as usize..],
+            offset + audio_start,
+            total_size,
+        );
+    }
+
+    probe_mp3_frames(data, offset, total_size)
+}
+
+fn probe_mp3_frames(data: &[u8], offset: u64, total_size: Option<u64>) -> MediaProbe {
+    // a candidate is confirmed by a matching header following it, if the window contains one
+    let first_frame = (0..data.len()).find_map(|pos| {
+        let header = FrameHeader::parse(&data[pos..])?;
+        let next = pos + header.frame_length();
+
+        match data.get(next..) {
+            Some(rest) if rest.len() >= 4 => FrameHeader::parse(rest)
+                .filter(|h| header.continues_with(h))
+                .map(|_| (pos, header)),
+            _ => Some((pos, header)),
+        }
+    });
+    let (pos, header) = match first_frame {
+        Some(frame) => frame,
+        None => return MediaProbe::Unknown,
+    };
+    let frame = &data[pos..];
+
+    if let Some(frames) = xing_frames(frame, &header).or_else(|| vbri_frames(frame)) {
+        return MediaProbe::Duration(
+            frames as f64 * header.samples_per_frame() as f64 / header.sample_rate as f64,
+        );
+    }
+
+    match total_size {
+        Some(size) if size > offset + pos as u64 => MediaProbe::Duration(
+            (size - offset - pos as u64) as f64 * 8.0 / (header.bitrate as f64 * 1000.0),
+        ),
+        _ => MediaProbe::Unknown,
+    }
+}
+
+fn xing_frames(frame: &[u8], header: &FrameHeader) -> Option<u32> {
+    let offset = header.xing_offset();
+    let tag = frame.get(offset..offset + 4)?;
+
+    if tag != b"Xing" && tag != b"Info" {
+        return None;
+    }
+
+    match read_u32(frame, offset + 4)? & 1 {
+        0 => None,
+        _ => read_u32(frame, offset + 8).filter(|f| *f > 0),
+    }
+}
+
+fn vbri_frames(frame: &[u8]) -> Option<u32> {
+    match frame.get(36..40)? {
+        b"VBRI" => read_u32(frame, 50).filter(|f| *f > 0),
+        _ => None,
+    }
+}
+
+fn synchsafe(data: &[u8]) -> u64 {
+    data.iter()
+        .fold(0, |size, b| (size << 7) | (b & 0x7F) as u64)
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(pos..pos + 8)?.try_into().ok()?))
+}
@@ -0,0 +1,158 @@
+use super::{content_range_total, probe_media, MediaProbe};
+use hyper::header::{HeaderMap, HeaderValue, CONTENT_RANGE};
+
+// MPEG 1 layer III, 128 kbit/s, 44.1 kHz, stereo; frames are 417 bytes long
+const FRAME_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x00];
+const FRAME_LENGTH: usize = 417;
+
+fn frame(tag: Option<(usize, &[u8])>) -> Vec<u8> {
+    let mut frame = vec![0; FRAME_LENGTH];
+
+    frame[..4].copy_from_slice(&FRAME_HEADER);
+
+    if let Some((offset, tag)) = tag {
+        frame[offset..offset + tag.len()].copy_from_slice(tag);
+    }
+
+    frame
+}
+
+fn id3(size: u8) -> Vec<u8> {
+    let mut tag = vec![b'I', b'D', b'3', 4, 0, 0, 0, 0, 0, size];
+
+    tag.resize(10 + size as usize, 0);
+    tag
+}
+
+fn mp4_box(box_type: &[u8], content: &[u8]) -> Vec<u8> {
+    let mut b = ((8 + content.len()) as u32).to_be_bytes().to_vec();
+
+    b.extend_from_slice(box_type);
+    b.extend_from_slice(content);
+    b
+}
+
+fn mvhd(timescale: u32, duration: u32) -> Vec<u8> {
+    let mut content = vec![0; 100];
+
+    content[12..16].copy_from_slice(&timescale.to_be_bytes());
+    content[16..20].copy_from_slice(&duration.to_be_bytes());
+    mp4_box(b"mvhd", &content)
+}
+
+fn assert_duration(probe: MediaProbe, expected: f64) {
+    match probe {
+        MediaProbe::Duration(d) => assert!((d - expected).abs() < 0.001, "{} != {}", d, expected),
+        p => panic!("expected a duration, got {:?}", p),
+    }
+}
+
+#[test]
+fn mp3_xing() {
+    // "Xing", flags (frame count present), frame count
+    let xing = [b"Xing".as_slice(), &[0, 0, 0, 1], &1000u32.to_be_bytes()].concat();
+    let data = [id3(100), frame(Some((36, &xing))), frame(None)].concat();
+
+    assert_duration(probe_media(&data, 0, None), 1000.0 * 1152.0 / 44100.0);
+}
+
+#[test]
+fn mp3_vbri() {
+    let mut vbri = b"VBRI".to_vec();
+
+    vbri.resize(14, 0);
+    vbri.extend_from_slice(&2000u32.to_be_bytes());
+
+    let data = [frame(Some((36, &vbri))), frame(None)].concat();
+
+    assert_duration(probe_media(&data, 0, None), 2000.0 * 1152.0 / 44100.0);
+}
+
+#[test]
+fn mp3_constant_bitrate() {
+    let data = [id3(90), frame(None), frame(None)].concat();
+
+    // 1.6 MB of audio at 128 kbit/s
+    assert_duration(probe_media(&data, 0, Some(100 + 1_600_000)), 100.0);
+    assert_eq!(probe_media(&data, 0, None), MediaProbe::Unknown);
+}
+
+#[test]
+fn mp3_large_id3_tag() {
+    // a tag of 1 MB, e.g. containing artwork
+    let data = [b"ID3".as_slice(), &[4, 0, 0, 0, 0x40, 0, 0], &[0; 100]].concat();
+
+    assert_eq!(
+        probe_media(&data, 0, None),
+        MediaProbe::NeedBytes(10 + 1024 * 1024)
+    );
+}
+
+#[test]
+fn mp4_moov_at_start() {
+    let data = [
+        mp4_box(b"ftyp", b"M4A \0\0\0\0"),
+        mp4_box(b"moov", &mvhd(600, 2_160_300)),
+        mp4_box(b"mdat", &[0; 64]),
+    ]
+    .concat();
+
+    assert_duration(probe_media(&data, 0, None), 3600.5);
+}
+
+#[test]
+fn mp4_moov_at_end() {
+    let mut data = mp4_box(b"ftyp", b"M4A \0\0\0\0");
+
+    data.extend_from_slice(&1_000_000u32.to_be_bytes());
+    data.extend_from_slice(b"mdat");
+    data.resize(100, 0);
+
+    assert_eq!(
+        probe_media(&data, 0, None),
+        MediaProbe::NeedBytes(1_000_016)
+    );
+
+    let data = mp4_box(b"moov", &mvhd(1000, 61_000));
+
+    assert_duration(probe_media(&data, 1_000_016, None), 61.0);
+}
+
+#[test]
+fn mp4_box_size_overflow() {
+    let mut data = mp4_box(b"ftyp", b"");
+
+    data.extend_from_slice(&1u32.to_be_bytes());
+    data.extend_from_slice(b"free");
+    data.extend_from_slice(&(u64::MAX - 7).to_be_bytes());
+
+    assert_eq!(probe_media(&data, 0, None), MediaProbe::Unknown);
+    assert_eq!(
+        probe_media(&mp4_box(b"ftyp", &[0; 8]), u64::MAX - 8, None),
+        MediaProbe::Unknown
+    );
+}
+
+#[test]
+fn unknown_media() {
+    assert_eq!(
+        probe_media(b"<html><body>not found</body></html>", 0, Some(1000)),
+        MediaProbe::Unknown
+    );
+}
+
+#[test]
+fn content_range() {
+    let mut headers = HeaderMap::new();
+
+    assert_eq!(content_range_total(&headers), None);
+
+    headers.insert(
+        CONTENT_RANGE,
+        HeaderValue::from_static("bytes 0-65535/12345678"),
+    );
+    assert_eq!(content_range_total(&headers), Some(12345678));
+
+    headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes 0-65535/*"));
+    assert_eq!(content_range_total(&headers), None);
+}
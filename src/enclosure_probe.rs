@@ -0,0 +1,146 @@
+use crate::{fetcher::Fetcher, repo::Repo};
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, Utc};
+use hyper::{
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+    Body, Method, Response,
+};
+use log::{error, info, trace, warn};
+use serde::{Deserialize, Serialize};
+use tokio::time::{sleep, Duration};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EnclosureProbeConfig {
+    pub interval_secs: u64,
+    /// maximum number of enclosures probed per run
+    pub batch_size: i64,
+    /// age after which an enclosure is probed again
+    pub max_age_secs: u64,
+}
+
+impl Default for EnclosureProbeConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 10 * 60,
+            batch_size: 100,
+            max_age_secs: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// What the server reports about an enclosure in response to a HEAD request.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EnclosureProbe {
+    pub size: Option<i64>,
+    pub mime_type: Option<String>,
+    /// the url after following all redirects
+    pub url: Option<String>,
+}
+
+impl EnclosureProbe {
+    pub fn from_response(res: &Response<Body>, urls: &[(String, i16)]) -> Self {
+        let headers = res.headers();
+
+        Self {
+            size: headers
+                .get(CONTENT_LENGTH)
+                .and_then(|l| l.to_str().ok())
+                .and_then(|l| l.trim().parse::<i64>().ok())
+                .filter(|l| *l > 0),
+            mime_type: headers
+                .get(CONTENT_TYPE)
+                .and_then(|t| t.to_str().ok())
+                .and_then(|t| t.split(';').next())
+                .map(|t| t.trim().to_lowercase())
+                .filter(|t| !t.is_empty() && t.len() <= 128),
+            url: urls.last().map(|(url, _)| url.clone()),
+        }
+    }
+}
+
+/// Background job recording the actual size, content type, and location of enclosures.
+pub struct EnclosureProber {
+    connection: String,
+    fetcher: Fetcher,
+    config: EnclosureProbeConfig,
+}
+
+impl EnclosureProber {
+    pub fn new(connection: &str, fetcher: Fetcher, config: EnclosureProbeConfig) -> Self {
+        Self {
+            connection: connection.into(),
+            fetcher,
+            config,
+        }
+    }
+
+    pub async fn probe_loop(&self) {
+        loop {
+            if let Err(e) = &self.probe_enclosures().await {
+                error!("error probing enclosures: {}", e);
+            }
+
+            sleep(Duration::from_secs(self.config.interval_secs)).await;
+        }
+    }
+
+    async fn probe_enclosures(&self) -> Result<()> {
+        trace!("probing enclosures");
+
+        let repo = Repo::new(&self.connection).await?;
+        let probed_before: DateTime<FixedOffset> = (Utc::now()
+            - chrono::Duration::from_std(Duration::from_secs(self.config.max_age_secs))?)
+        .into();
+        let items = repo
+            .get_items_to_probe(&probed_before, self.config.batch_size)
+            .await?;
+
+        trace!("got {} enclosures to probe", items.len());
+
+        for item in items {
+            // failed probes are recorded as well, so that they are not retried on every run
+            let probe = match self
+                .fetcher
+                .request(&item.enclosure_url, &Method::HEAD)
+                .await
+            {
+                Ok((Some(res), urls)) => EnclosureProbe::from_response(&res, &urls),
+                Ok((None, urls)) => {
+                    warn!(
+                        "HEAD request for \"{}\" failed with status {:?}",
+                        item.enclosure_url,
+                        urls.last().map(|(_, status)| status)
+                    );
+                    EnclosureProbe::default()
+                }
+                Err(e) => {
+                    warn!("error probing \"{}\": {}", item.enclosure_url, e);
+                    EnclosureProbe::default()
+                }
+            };
+
+            if let Err(e) = repo.update_item_probe(&item.id, &probe).await {
+                error!("error recording probe of \"{}\": {}", item.enclosure_url, e);
+
+                // unprobed items come first, so an item, whose probe cannot be stored, would block the queue
+                if let Err(e) = repo
+                    .update_item_probe(&item.id, &EnclosureProbe::default())
+                    .await
+                {
+                    error!(
+                        "error recording failed probe of \"{}\": {}",
+                        item.enclosure_url, e
+                    );
+                }
+            }
+        }
+
+        info!("probed enclosures");
+
+        Ok(())
+    }
+}
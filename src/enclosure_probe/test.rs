@@ -0,0 +1,45 @@
+use super::EnclosureProbe;
+use hyper::{Body, Response};
+
+#[test]
+fn probe_from_response() {
+    let res = Response::builder()
+        .header("Content-Length", "12345678")
+        .header("Content-Type", "Audio/MPEG; charset=binary")
+        .body(Body::empty())
+        .unwrap();
+    let urls = vec![
+        (
+            String::from("https://dts.podtrac.com/redirect.mp3/example.com/1.mp3"),
+            302,
+        ),
+        (String::from("https://cdn.example.com/1.mp3"), 200),
+    ];
+
+    assert_eq!(
+        EnclosureProbe::from_response(&res, &urls),
+        EnclosureProbe {
+            size: Some(12345678),
+            mime_type: Some(String::from("audio/mpeg")),
+            url: Some(String::from("https://cdn.example.com/1.mp3")),
+        }
+    );
+}
+
+#[test]
+fn probe_without_headers() {
+    let res = Response::builder()
+        .header("Content-Length", "0")
+        .body(Body::empty())
+        .unwrap();
+    let urls = vec![(String::from("https://example.com/1.mp3"), 200)];
+
+    assert_eq!(
+        EnclosureProbe::from_response(&res, &urls),
+        EnclosureProbe {
+            size: None,
+            mime_type: None,
+            url: Some(String::from("https://example.com/1.mp3")),
+        }
+    );
+}
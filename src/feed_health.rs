@@ -0,0 +1,116 @@
+use crate::rss_feed::Diagnostic;
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::Serialize;
+use std::convert::TryFrom;
+use uuid::Uuid;
+
+#[cfg(test)]
+mod test;
+
+// number of consecutive failed fetches after which a feed is considered failing
+const FAILING_THRESHOLD: usize = 3;
+/// Number of fetches kept per feed.
+pub const HEALTH_HISTORY_LENGTH: i64 = 20;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedFetchLog {
+    pub id: Uuid,
+    pub feed_id: Uuid,
+    pub url: Option<String>,
+    pub status: Option<i16>,
+    pub error: Option<String>,
+    pub channel_count: i32,
+    pub item_count: i32,
+    pub duration_ms: i64,
+    pub fetch_ts: DateTime<FixedOffset>,
+}
+
+impl FeedFetchLog {
+    pub fn new(feed_id: &Uuid) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            feed_id: *feed_id,
+            url: None,
+            status: None,
+            error: None,
+            channel_count: 0,
+            item_count: 0,
+            duration_ms: 0,
+            fetch_ts: Utc::now().into(),
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl TryFrom<&tokio_postgres::Row> for FeedFetchLog {
+    type Error = anyhow::Error;
+
+    fn try_from(row: &tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            feed_id: row.try_get("feed_id")?,
+            url: row.try_get("url")?,
+            status: row.try_get("status")?,
+            error: row.try_get("error")?,
+            channel_count: row.try_get("channel_count")?,
+            item_count: row.try_get("item_count")?,
+            duration_ms: row.try_get("duration_ms")?,
+            fetch_ts: row.try_get("fetch_ts")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum FeedHealthState {
+    Unknown,
+    Healthy,
+    Degraded,
+    Failing,
+}
+
+/// The problems found in a feed during its latest fetch.
+#[derive(Debug, Serialize)]
+pub struct FeedDiagnostics {
+    pub feed_id: Uuid,
+    pub fetch: Option<FeedFetchLog>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeedHealth {
+    pub feed_id: Uuid,
+    pub state: FeedHealthState,
+    pub consecutive_failures: usize,
+    pub last_success: Option<DateTime<FixedOffset>>,
+    pub last_failure: Option<DateTime<FixedOffset>>,
+    pub fetches: Vec<FeedFetchLog>,
+}
+
+impl FeedHealth {
+    /// Computes the health of a feed from its fetch history (newest first).
+    pub fn from_logs(feed_id: &Uuid, fetches: Vec<FeedFetchLog>) -> Self {
+        let consecutive_failures = fetches.iter().take_while(|f| !f.is_success()).count();
+        let last_success = fetches.iter().find(|f| f.is_success()).map(|f| f.fetch_ts);
+        let last_failure = fetches.iter().find(|f| !f.is_success()).map(|f| f.fetch_ts);
+
+        let state = match (fetches.len(), consecutive_failures, last_success) {
+            (0, _, _) => FeedHealthState::Unknown,
+            (_, 0, _) => FeedHealthState::Healthy,
+            (_, n, Some(_)) if n < FAILING_THRESHOLD => FeedHealthState::Degraded,
+            _ => FeedHealthState::Failing,
+        };
+
+        Self {
+            feed_id: *feed_id,
+            state,
+            consecutive_failures,
+            last_success,
+            last_failure,
+            fetches,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use super::{FeedFetchLog, FeedHealth, FeedHealthState};
+use uuid::Uuid;
+
+fn fetches(results: &[bool]) -> Vec<FeedFetchLog> {
+    let feed_id = Uuid::new_v4();
+
+    results
+        .iter()
+        .map(|&ok| {
+            let mut log = FeedFetchLog::new(&feed_id);
+
+            if !ok {
+                log.error = Some("error".into());
+            }
+
+            log
+        })
+        .collect()
+}
+
+#[test]
+fn health_unknown() {
+    let health = FeedHealth::from_logs(&Uuid::new_v4(), vec![]);
+
+    assert_eq!(health.state, FeedHealthState::Unknown);
+    assert_eq!(health.consecutive_failures, 0);
+}
+
+#[test]
+fn health_healthy() {
+    let health = FeedHealth::from_logs(&Uuid::new_v4(), fetches(&[true, false, false]));
+
+    assert_eq!(health.state, FeedHealthState::Healthy);
+    assert_eq!(health.consecutive_failures, 0);
+    assert!(health.last_failure.is_some());
+}
+
+#[test]
+fn health_degraded() {
+    let health = FeedHealth::from_logs(&Uuid::new_v4(), fetches(&[false, false, true]));
+
+    assert_eq!(health.state, FeedHealthState::Degraded);
+    assert_eq!(health.consecutive_failures, 2);
+}
+
+#[test]
+fn health_failing() {
+    assert_eq!(
+        FeedHealth::from_logs(&Uuid::new_v4(), fetches(&[false, false, false, true])).state,
+        FeedHealthState::Failing
+    );
+    assert_eq!(
+        FeedHealth::from_logs(&Uuid::new_v4(), fetches(&[false])).state,
+        FeedHealthState::Failing
+    );
+}
@@ -0,0 +1,102 @@
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use tokio::time::Duration;
+use uuid::Uuid;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BackoffConfig {
+    pub base_secs: u64,
+    pub max_secs: u64,
+    pub disable_threshold: i32,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            base_secs: 60 * 60,
+            max_secs: 7 * 24 * 60 * 60,
+            disable_threshold: 10,
+        }
+    }
+}
+
+impl BackoffConfig {
+    /// Delay before the next fetch after the given number of consecutive failures.
+    pub fn delay(&self, failures: i32) -> Duration {
+        if failures <= 0 {
+            return Duration::from_secs(0);
+        }
+
+        let factor = 2u64.checked_pow((failures - 1) as u32).unwrap_or(u64::MAX);
+
+        Duration::from_secs(self.base_secs.saturating_mul(factor).min(self.max_secs))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedSchedule {
+    pub feed_id: Uuid,
+    pub consecutive_failures: i32,
+    pub next_fetch_ts: Option<DateTime<FixedOffset>>,
+    pub disabled: bool,
+    pub update_ts: DateTime<FixedOffset>,
+}
+
+impl FeedSchedule {
+    pub fn new(feed_id: &Uuid) -> Self {
+        Self {
+            feed_id: *feed_id,
+            consecutive_failures: 0,
+            next_fetch_ts: None,
+            disabled: false,
+            update_ts: Utc::now().into(),
+        }
+    }
+
+    pub fn is_due(&self, now: &DateTime<FixedOffset>) -> bool {
+        !self.disabled && self.next_fetch_ts.is_none_or(|ts| ts <= *now)
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.next_fetch_ts = None;
+        self.update_ts = Utc::now().into();
+    }
+
+    pub fn record_failure(&mut self, config: &BackoffConfig, now: &DateTime<FixedOffset>) {
+        self.consecutive_failures += 1;
+        self.next_fetch_ts = Some(
+            *now + chrono::Duration::from_std(config.delay(self.consecutive_failures))
+                .unwrap_or_else(|_| chrono::Duration::zero()),
+        );
+        self.disabled = self.consecutive_failures >= config.disable_threshold;
+        self.update_ts = Utc::now().into();
+    }
+
+    pub fn enable(&mut self) {
+        self.consecutive_failures = 0;
+        self.next_fetch_ts = None;
+        self.disabled = false;
+        self.update_ts = Utc::now().into();
+    }
+}
+
+impl TryFrom<&tokio_postgres::Row> for FeedSchedule {
+    type Error = anyhow::Error;
+
+    fn try_from(row: &tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            feed_id: row.try_get("feed_id")?,
+            consecutive_failures: row.try_get("consecutive_failures")?,
+            next_fetch_ts: row.try_get("next_fetch_ts")?,
+            disabled: row.try_get("disabled")?,
+            update_ts: row.try_get("update_ts")?,
+        })
+    }
+}
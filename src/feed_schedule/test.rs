@@ -0,0 +1,59 @@
+use super::{BackoffConfig, FeedSchedule};
+use chrono::{DateTime, FixedOffset, Utc};
+use tokio::time::Duration;
+use uuid::Uuid;
+
+#[test]
+fn backoff_delay() {
+    let config = BackoffConfig {
+        base_secs: 60,
+        max_secs: 600,
+        disable_threshold: 5,
+    };
+
+    assert_eq!(config.delay(0), Duration::from_secs(0));
+    assert_eq!(config.delay(1), Duration::from_secs(60));
+    assert_eq!(config.delay(2), Duration::from_secs(120));
+    assert_eq!(config.delay(4), Duration::from_secs(480));
+    assert_eq!(config.delay(5), Duration::from_secs(600));
+    assert_eq!(config.delay(100), Duration::from_secs(600));
+}
+
+#[test]
+fn failures_delay_and_disable() {
+    let config = BackoffConfig {
+        base_secs: 60,
+        max_secs: 600,
+        disable_threshold: 3,
+    };
+    let now: DateTime<FixedOffset> = Utc::now().into();
+    let mut schedule = FeedSchedule::new(&Uuid::new_v4());
+
+    assert!(schedule.is_due(&now));
+
+    schedule.record_failure(&config, &now);
+    assert!(!schedule.is_due(&now));
+    assert!(schedule.is_due(&(now + chrono::Duration::seconds(60))));
+    assert!(!schedule.disabled);
+
+    schedule.record_failure(&config, &now);
+    schedule.record_failure(&config, &now);
+    assert!(schedule.disabled);
+    assert!(!schedule.is_due(&(now + chrono::Duration::days(365))));
+
+    schedule.enable();
+    assert!(schedule.is_due(&now));
+    assert_eq!(schedule.consecutive_failures, 0);
+}
+
+#[test]
+fn success_resets_failures() {
+    let now: DateTime<FixedOffset> = Utc::now().into();
+    let mut schedule = FeedSchedule::new(&Uuid::new_v4());
+
+    schedule.record_failure(&BackoffConfig::default(), &now);
+    schedule.record_success();
+
+    assert!(schedule.is_due(&now));
+    assert_eq!(schedule.consecutive_failures, 0);
+}
@@ -1,57 +1,486 @@
 use anyhow::{anyhow, Context, Result};
-use hyper::{client::HttpConnector, Body, Client, Method, Request, Response, StatusCode};
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use hyper::{
+    body::{Bytes, HttpBody},
+    client::HttpConnector,
+    header::{
+        HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, LOCATION, RANGE,
+        USER_AGENT,
+    },
+    Body, Client, Method, Request, Response, StatusCode, Uri,
+};
+use hyper_proxy::{Intercept, Proxy, ProxyConnector};
 use hyper_tls::HttpsConnector;
-use tokio::time::{self, Duration};
-
-pub async fn request(
-    url: &str,
-    timeout: &Duration,
-    method: &Method,
-) -> Result<(Option<Response<Body>>, Vec<(String, i16)>)> {
-    let mut urls: Vec<(String, i16)> = Vec::new();
-    let mut res = time::timeout(*timeout, internal_request(url, method)).await??;
-    urls.push((url.into(), res.status().as_u16() as i16));
-
-    while (res.status() == StatusCode::TEMPORARY_REDIRECT)
-        || (res.status() == StatusCode::FOUND)
-        || (res.status() == StatusCode::PERMANENT_REDIRECT)
-        || (res.status() == StatusCode::MOVED_PERMANENTLY)
-    {
-        let next_url = res.headers()["location"].to_str()?.to_string();
-
-        res = time::timeout(*timeout, internal_request(&*next_url, method)).await??;
-        urls.push((next_url.into(), res.status().as_u16() as i16));
-    }
-
-    if res.status() == StatusCode::OK {
-        Ok((Some(res), urls))
-    } else {
-        Ok((None, urls))
-    }
-}
-
-async fn internal_request(url: &str, method: &Method) -> Result<Response<Body>> {
-    let uri: hyper::Uri = url.parse()?;
-
-    let req = Request::builder()
-        .method(method)
-        .uri(url)
-        .body(Body::empty())?;
-
-    match uri.scheme_str() {
-        Some(s) => match s {
-            "http" => Client::builder()
-                .build::<_, Body>(HttpConnector::new())
-                .request(req)
-                .await
-                .context("request failed"),
-            "https" => Client::builder()
-                .build::<_, Body>(HttpsConnector::new())
-                .request(req)
-                .await
-                .context("request failed"),
-            _ => Err(anyhow!("no connector available for scheme \"{}\"", s)),
-        },
-        None => Err(anyhow!("scheme not recognized")),
+use native_tls::{Certificate, TlsConnector};
+use serde::Deserialize;
+use std::io::Read;
+use tokio::{
+    task,
+    time::{self, Duration, Instant},
+};
+use tokio_native_tls::TlsConnector as TokioTlsConnector;
+use url::Url;
+
+const MAX_REDIRECTS: usize = 10;
+
+/// Time by which a request chain including reading its body must be completed.
+#[derive(Debug, Clone, Copy)]
+struct Deadline(Instant);
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FetcherConfig {
+    pub user_agent: String,
+    pub connect_timeout_secs: u64,
+    /// time allowed until the response headers arrived (per hop)
+    pub timeout_secs: u64,
+    /// time allowed for the whole redirect chain and reading the body
+    pub total_timeout_secs: u64,
+    pub proxy: Option<String>,
+    /// path to a PEM file with additional root certificates
+    pub ca_bundle: Option<String>,
+    /// maximum size of a response body as transferred in bytes
+    pub max_body_size: usize,
+    /// maximum size of a decompressed response body in bytes
+    pub max_decompressed_size: usize,
+    /// minimum average transfer rate of a response body in bytes per second (0 disables the check)
+    pub min_transfer_rate: u64,
+    /// time before the minimum transfer rate is enforced
+    pub transfer_grace_secs: u64,
+}
+
+impl Default for FetcherConfig {
+    fn default() -> Self {
+        Self {
+            user_agent: format!("podcast-player-api/{}", env!("CARGO_PKG_VERSION")),
+            connect_timeout_secs: 3,
+            timeout_secs: 10,
+            total_timeout_secs: 60,
+            proxy: None,
+            ca_bundle: None,
+            max_body_size: 32 * 1024 * 1024,
+            max_decompressed_size: 64 * 1024 * 1024,
+            min_transfer_rate: 1024,
+            transfer_grace_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Fetcher {
+    client: Client<ProxyConnector<HttpsConnector<HttpConnector>>>,
+    user_agent: HeaderValue,
+    timeout: Duration,
+    total_timeout: Duration,
+    max_body_size: usize,
+    max_decompressed_size: usize,
+    min_transfer_rate: u64,
+    transfer_grace: Duration,
+}
+
+impl Fetcher {
+    pub fn new(config: &FetcherConfig) -> Result<Self> {
+        let mut http = HttpConnector::new();
+
+        http.enforce_http(false);
+        http.set_connect_timeout(Some(Duration::from_secs(config.connect_timeout_secs)));
+
+        let mut tls_builder = TlsConnector::builder();
+
+        if let Some(path) = &config.ca_bundle {
+            for certificate in read_ca_bundle(path)? {
+                tls_builder.add_root_certificate(certificate);
+            }
+        }
+
+        let tls = tls_builder.build()?;
+        let https = HttpsConnector::from((http, TokioTlsConnector::from(tls.clone())));
+        let connector = match &config.proxy {
+            Some(proxy) => {
+                let proxy = Proxy::new(
+                    Intercept::All,
+                    proxy.parse::<Uri>().context("invalid proxy url")?,
+                );
+                let mut connector = ProxyConnector::from_proxy(https, proxy)?;
+
+                connector.set_tls(Some(tls));
+                connector
+            }
+            None => ProxyConnector::new(https)?,
+        };
+
+        Ok(Self {
+            client: Client::builder().build(connector),
+            user_agent: HeaderValue::from_str(&config.user_agent)?,
+            timeout: Duration::from_secs(config.timeout_secs),
+            total_timeout: Duration::from_secs(config.total_timeout_secs),
+            max_body_size: config.max_body_size,
+            max_decompressed_size: config.max_decompressed_size,
+            min_transfer_rate: config.min_transfer_rate,
+            transfer_grace: Duration::from_secs(config.transfer_grace_secs),
+        })
+    }
+
+    /// Requests the url as is; the body is passed on without decoding (e.g., for streaming).
+    pub async fn request(
+        &self,
+        url: &str,
+        method: &Method,
+    ) -> Result<(Option<Response<Body>>, Vec<(String, i16)>)> {
+        self.request_chain(url, method, false, None).await
+    }
+
+    /// Requests the url accepting compressed transfer; the body must be read with `read_body`.
+    pub async fn request_compressed(
+        &self,
+        url: &str,
+        method: &Method,
+    ) -> Result<(Option<Response<Body>>, Vec<(String, i16)>)> {
+        self.request_chain(url, method, true, None).await
+    }
+
+    /// Requests `length` bytes starting at `start`; the response is either partial content or,
+    /// if the server ignores the range, the whole body.
+    pub async fn request_range(
+        &self,
+        url: &str,
+        start: u64,
+        length: u64,
+    ) -> Result<(Option<Response<Body>>, Vec<(String, i16)>)> {
+        self.request_chain(url, &Method::GET, false, Some((start, length)))
+            .await
+    }
+
+    /// Reads the complete body of a response decoding any content encoding.
+    ///
+    /// Bodies exceeding the maximum size or transferred below the minimum rate are rejected.
+    pub async fn read_body(&self, res: Response<Body>) -> Result<Vec<u8>> {
+        let (parts, body) = res.into_parts();
+        let deadline = parts.extensions.get::<Deadline>().map(|d| d.0);
+
+        self.read_body_until(&parts.headers, body, deadline).await
+    }
+
+    /// Reads a body like `read_body`, e.g. of a feed uploaded to the service.
+    pub async fn read_message_body(&self, headers: &HeaderMap, body: Body) -> Result<Vec<u8>> {
+        self.read_body_until(headers, body, None).await
+    }
+
+    async fn read_body_until(
+        &self,
+        headers: &HeaderMap,
+        mut body: Body,
+        deadline: Option<Instant>,
+    ) -> Result<Vec<u8>> {
+        let encoding = headers
+            .get(CONTENT_ENCODING)
+            .map(|e| e.to_str())
+            .transpose()
+            .context("content encoding header contains invalid characters")?
+            .map(String::from);
+        let content_length = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|l| l.to_str().ok())
+            .and_then(|l| l.parse::<usize>().ok());
+
+        if let Some(length) = content_length {
+            if length > self.max_body_size {
+                return Err(anyhow!(
+                    "body of {} bytes exceeds the maximum size of {} bytes",
+                    length,
+                    self.max_body_size
+                ));
+            }
+        }
+
+        let mut buf = Vec::<u8>::new();
+        let start = Instant::now();
+
+        loop {
+            let chunk = match deadline {
+                Some(deadline) => {
+                    time::timeout_at(deadline, self.read_chunk(&mut body, start, buf.len()))
+                        .await
+                        .map_err(|_| anyhow!("timeout reading body"))??
+                }
+                None => self.read_chunk(&mut body, start, buf.len()).await?,
+            };
+
+            match chunk {
+                Some(chunk) => {
+                    let chunk = chunk?;
+
+                    if buf.len() + chunk.len() > self.max_body_size {
+                        return Err(anyhow!(
+                            "body exceeds the maximum size of {} bytes",
+                            self.max_body_size
+                        ));
+                    }
+
+                    buf.extend_from_slice(&chunk);
+                }
+                None => break,
+            }
+        }
+
+        let limit = self.max_decompressed_size;
+
+        // decompressing large bodies would block the runtime
+        task::spawn_blocking(move || decode_body(encoding.as_deref(), &buf, limit)).await?
+    }
+
+    /// Reads the next chunk of a body, unless the transfer rate dropped below the minimum.
+    async fn read_chunk(
+        &self,
+        body: &mut Body,
+        start: Instant,
+        received: usize,
+    ) -> Result<Option<Result<Bytes, hyper::Error>>> {
+        match self.min_transfer_rate {
+            0 => Ok(body.data().await),
+            rate => {
+                // the time at which the bytes received so far fall below the minimum rate
+                let deadline = start
+                    + self.transfer_grace
+                    + Duration::from_secs_f64(received as f64 / rate as f64);
+
+                time::timeout_at(deadline, body.data())
+                    .await
+                    .map_err(|_| anyhow!("transfer rate dropped below {} bytes/s", rate))
+            }
+        }
+    }
+
+    /// Reads at most `limit` bytes of an undecoded body; the rest of the body is discarded.
+    pub async fn read_body_prefix(&self, res: Response<Body>, limit: usize) -> Result<Vec<u8>> {
+        let deadline = res.extensions().get::<Deadline>().map(|d| d.0);
+        let mut body = res.into_body();
+        let mut buf = Vec::<u8>::new();
+
+        while buf.len() < limit {
+            let chunk_deadline = Instant::now() + self.timeout;
+
+            match time::timeout_at(
+                deadline.map_or(chunk_deadline, |d| d.min(chunk_deadline)),
+                body.data(),
+            )
+            .await
+            .map_err(|_| anyhow!("timeout reading body"))?
+            {
+                Some(chunk) => buf.extend_from_slice(&chunk?),
+                None => break,
+            }
+        }
+
+        buf.truncate(limit);
+
+        Ok(buf)
+    }
+
+    async fn request_chain(
+        &self,
+        url: &str,
+        method: &Method,
+        compressed: bool,
+        range: Option<(u64, u64)>,
+    ) -> Result<(Option<Response<Body>>, Vec<(String, i16)>)> {
+        let mut urls: Vec<(String, i16)> = Vec::new();
+        let mut current_url = String::from(url);
+        let mut current_method = method.clone();
+        let deadline = Instant::now() + self.total_timeout;
+        let mut res = self
+            .timed_request(url, method, compressed, range, deadline)
+            .await?;
+        urls.push((url.into(), res.status().as_u16() as i16));
+
+        while is_redirect(res.status()) {
+            if urls.len() > MAX_REDIRECTS {
+                return Err(anyhow!("too many redirects requesting \"{}\"", url));
+            }
+
+            let next_url = redirect_location(&current_url, res.headers().get(LOCATION))?;
+
+            if urls.iter().any(|(u, _)| *u == next_url) {
+                return Err(anyhow!("redirect loop detected at \"{}\"", next_url));
+            }
+
+            current_method = redirect_method(res.status(), &current_method);
+            res = self
+                .timed_request(&next_url, &current_method, compressed, range, deadline)
+                .await?;
+            urls.push((next_url.clone(), res.status().as_u16() as i16));
+            current_url = next_url;
+        }
+
+        if res.status() == StatusCode::OK
+            || (range.is_some() && res.status() == StatusCode::PARTIAL_CONTENT)
+        {
+            Ok((Some(res), urls))
+        } else {
+            Ok((None, urls))
+        }
+    }
+
+    /// Requests a single hop; the deadline of the chain is passed on to reading the body.
+    async fn timed_request(
+        &self,
+        url: &str,
+        method: &Method,
+        compressed: bool,
+        range: Option<(u64, u64)>,
+        deadline: Instant,
+    ) -> Result<Response<Body>> {
+        let hop_deadline = (Instant::now() + self.timeout).min(deadline);
+        let mut res = time::timeout_at(
+            hop_deadline,
+            self.internal_request(url, method, compressed, range),
+        )
+        .await
+        .map_err(|_| anyhow!("timeout requesting \"{}\"", url))??;
+
+        res.extensions_mut().insert(Deadline(deadline));
+
+        Ok(res)
+    }
+
+    async fn internal_request(
+        &self,
+        url: &str,
+        method: &Method,
+        compressed: bool,
+        range: Option<(u64, u64)>,
+    ) -> Result<Response<Body>> {
+        let uri: Uri = url.parse()?;
+
+        match uri.scheme_str() {
+            Some("http" | "https") => {
+                let mut builder = Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header(USER_AGENT, self.user_agent.clone());
+
+                if compressed {
+                    builder = builder.header(ACCEPT_ENCODING, "gzip, deflate, br");
+                }
+
+                if let Some((start, length)) = range {
+                    builder = builder.header(
+                        RANGE,
+                        format!("bytes={}-{}", start, start + length.max(1) - 1),
+                    );
+                }
+
+                let req = builder.body(Body::empty())?;
+
+                self.client.request(req).await.context("request failed")
+            }
+            Some(s) => Err(anyhow!("no connector available for scheme \"{}\"", s)),
+            None => Err(anyhow!("scheme not recognized")),
+        }
+    }
+}
+
+fn read_ca_bundle(path: &str) -> Result<Vec<Certificate>> {
+    let pem = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read ca bundle \"{}\"", path))?;
+
+    pem.split_inclusive("-----END CERTIFICATE-----")
+        .filter(|c| c.contains("-----BEGIN CERTIFICATE-----"))
+        .map(|c| Certificate::from_pem(c.trim().as_bytes()).context("invalid certificate"))
+        .collect()
+}
+
+/// Decodes a body according to its content encoding header; codings are undone in reverse order.
+fn decode_body(encoding: Option<&str>, body: &[u8], limit: usize) -> Result<Vec<u8>> {
+    let mut buf = body.to_vec();
+
+    for coding in encoding.unwrap_or_default().rsplit(',') {
+        buf = match &*coding.trim().to_lowercase() {
+            "" | "identity" => buf,
+            "gzip" | "x-gzip" => read_limited(GzDecoder::new(&*buf), limit)?,
+            // "deflate" should be zlib wrapped, but some servers send raw deflate streams
+            "deflate" => read_limited(ZlibDecoder::new(&*buf), limit)
+                .or_else(|_| read_limited(DeflateDecoder::new(&*buf), limit))?,
+            "br" => read_limited(brotli::Decompressor::new(&*buf, 4096), limit)?,
+            c => return Err(anyhow!("unsupported content encoding \"{}\"", c)),
+        };
+    }
+
+    if buf.len() > limit {
+        return Err(anyhow!("body exceeds {} bytes", limit));
+    }
+
+    Ok(buf)
+}
+
+fn read_limited<R: Read>(reader: R, limit: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+
+    reader
+        .take(limit as u64 + 1)
+        .read_to_end(&mut buf)
+        .context("error decompressing body")?;
+
+    if buf.len() > limit {
+        return Err(anyhow!("decompressed body exceeds {} bytes", limit));
+    }
+
+    Ok(buf)
+}
+
+fn is_redirect(status: StatusCode) -> bool {
+    (status == StatusCode::MOVED_PERMANENTLY)
+        || (status == StatusCode::FOUND)
+        || (status == StatusCode::SEE_OTHER)
+        || (status == StatusCode::TEMPORARY_REDIRECT)
+        || (status == StatusCode::PERMANENT_REDIRECT)
+}
+
+/// Resolves the location header of a redirect relative to the url that was requested.
+fn redirect_location(current_url: &str, location: Option<&HeaderValue>) -> Result<String> {
+    let location = location
+        .ok_or(anyhow!(
+            "redirect from \"{}\" without location header",
+            current_url
+        ))?
+        .to_str()
+        .context("location header contains invalid characters")?;
+
+    Ok(Url::parse(current_url)?
+        .join(location.trim())
+        .context("could not resolve location header")?
+        .to_string())
+}
+
+/// 303 is followed with GET (HEAD stays HEAD); 307 and 308 must keep the method.
+fn redirect_method(status: StatusCode, method: &Method) -> Method {
+    match (status, method) {
+        (StatusCode::SEE_OTHER, &Method::HEAD) => Method::HEAD,
+        (StatusCode::SEE_OTHER, _) => Method::GET,
+        (StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND, &Method::POST) => Method::GET,
+        _ => method.clone(),
+    }
+}
+
+/// Returns the index of the url a request chain was permanently redirected to, if any.
+///
+/// Only a successful chain starting with permanent redirects (301/308) qualifies.
+pub fn permanent_redirect_target(urls: &[(String, i16)]) -> Option<usize> {
+    let is_permanent = |status: i16| {
+        status == StatusCode::MOVED_PERMANENTLY.as_u16() as i16
+            || status == StatusCode::PERMANENT_REDIRECT.as_u16() as i16
+    };
+
+    match urls.last() {
+        Some((_, status)) if *status == StatusCode::OK.as_u16() as i16 => {
+            match urls.iter().take_while(|(_, s)| is_permanent(*s)).count() {
+                0 => None,
+                hops => Some(hops),
+            }
+        }
+        _ => None,
     }
 }
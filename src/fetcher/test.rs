@@ -0,0 +1,296 @@
+use super::{
+    decode_body, permanent_redirect_target, redirect_location, redirect_method, Deadline, Fetcher,
+    FetcherConfig,
+};
+use flate2::{
+    write::{DeflateEncoder, GzEncoder, ZlibEncoder},
+    Compression,
+};
+use hyper::{header::HeaderValue, Body, Method, Response, StatusCode};
+use std::{fs, io::Write};
+use tokio::time::{sleep, Duration, Instant};
+
+fn chain(statuses: &[i16]) -> Vec<(String, i16)> {
+    statuses
+        .iter()
+        .enumerate()
+        .map(|(i, &s)| (format!("https://example.com/{}", i), s))
+        .collect()
+}
+
+#[test]
+fn permanent_redirect() {
+    assert_eq!(permanent_redirect_target(&chain(&[301, 200])), Some(1));
+    assert_eq!(permanent_redirect_target(&chain(&[308, 301, 200])), Some(2));
+    assert_eq!(permanent_redirect_target(&chain(&[301, 302, 200])), Some(1));
+}
+
+#[test]
+fn no_permanent_redirect() {
+    assert_eq!(permanent_redirect_target(&chain(&[200])), None);
+    assert_eq!(permanent_redirect_target(&chain(&[302, 301, 200])), None);
+    assert_eq!(permanent_redirect_target(&chain(&[301, 404])), None);
+}
+
+#[test]
+fn location_absolute() {
+    assert_eq!(
+        redirect_location(
+            "http://example.com/feed.xml",
+            Some(&HeaderValue::from_static("https://example.org/rss"))
+        )
+        .unwrap(),
+        "https://example.org/rss"
+    );
+}
+
+#[test]
+fn location_relative() {
+    assert_eq!(
+        redirect_location(
+            "https://example.com/podcast/feed.xml",
+            Some(&HeaderValue::from_static("/new/feed.xml"))
+        )
+        .unwrap(),
+        "https://example.com/new/feed.xml"
+    );
+    assert_eq!(
+        redirect_location(
+            "https://example.com/podcast/feed.xml",
+            Some(&HeaderValue::from_static("rss.xml?page=1"))
+        )
+        .unwrap(),
+        "https://example.com/podcast/rss.xml?page=1"
+    );
+    assert_eq!(
+        redirect_location(
+            "https://example.com/podcast/feed.xml",
+            Some(&HeaderValue::from_static("//cdn.example.com/feed.xml"))
+        )
+        .unwrap(),
+        "https://cdn.example.com/feed.xml"
+    );
+}
+
+#[test]
+fn location_missing() {
+    assert!(redirect_location("https://example.com/feed.xml", None).is_err());
+}
+
+#[test]
+fn method_after_redirect() {
+    assert_eq!(
+        redirect_method(StatusCode::SEE_OTHER, &Method::HEAD),
+        Method::HEAD
+    );
+    assert_eq!(
+        redirect_method(StatusCode::SEE_OTHER, &Method::POST),
+        Method::GET
+    );
+    assert_eq!(
+        redirect_method(StatusCode::TEMPORARY_REDIRECT, &Method::HEAD),
+        Method::HEAD
+    );
+    assert_eq!(
+        redirect_method(StatusCode::TEMPORARY_REDIRECT, &Method::POST),
+        Method::POST
+    );
+    assert_eq!(
+        redirect_method(StatusCode::FOUND, &Method::GET),
+        Method::GET
+    );
+}
+
+#[test]
+fn fetcher_config() {
+    assert!(Fetcher::new(&FetcherConfig::default()).is_ok());
+    assert!(Fetcher::new(&FetcherConfig {
+        proxy: Some(String::from("http://proxy.example.com:3128")),
+        ..Default::default()
+    })
+    .is_ok());
+    assert!(Fetcher::new(&FetcherConfig {
+        ca_bundle: Some(String::from("testFiles/does_not_exist.pem")),
+        ..Default::default()
+    })
+    .is_err());
+}
+
+#[test]
+fn decode_identity() {
+    let feed = fs::read("testFiles/bots.xml").unwrap();
+
+    assert_eq!(decode_body(None, &feed, feed.len()).unwrap(), feed);
+    assert_eq!(
+        decode_body(Some("identity"), &feed, feed.len()).unwrap(),
+        feed
+    );
+    assert!(decode_body(None, &feed, feed.len() - 1).is_err());
+}
+
+#[test]
+fn decode_gzip() {
+    let feed = fs::read("testFiles/exponent.xml").unwrap();
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+
+    encoder.write_all(&feed).unwrap();
+
+    let body = encoder.finish().unwrap();
+
+    assert_eq!(decode_body(Some("gzip"), &body, feed.len()).unwrap(), feed);
+}
+
+#[test]
+fn decode_deflate() {
+    let feed = fs::read("testFiles/exponent.xml").unwrap();
+    let mut zlib_encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let mut raw_encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+
+    zlib_encoder.write_all(&feed).unwrap();
+    raw_encoder.write_all(&feed).unwrap();
+
+    let zlib_body = zlib_encoder.finish().unwrap();
+    let raw_body = raw_encoder.finish().unwrap();
+
+    assert_eq!(
+        decode_body(Some("deflate"), &zlib_body, feed.len()).unwrap(),
+        feed
+    );
+    assert_eq!(
+        decode_body(Some("Deflate"), &raw_body, feed.len()).unwrap(),
+        feed
+    );
+}
+
+#[test]
+fn decode_brotli() {
+    let feed = fs::read("testFiles/exponent.xml").unwrap();
+    let mut body = Vec::new();
+
+    {
+        let mut encoder = brotli::CompressorWriter::new(&mut body, 4096, 9, 22);
+
+        encoder.write_all(&feed).unwrap();
+    }
+
+    assert_eq!(decode_body(Some("br"), &body, feed.len()).unwrap(), feed);
+}
+
+#[test]
+fn decode_bomb() {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+
+    encoder.write_all(&vec![0u8; 10 * 1024 * 1024]).unwrap();
+
+    let body = encoder.finish().unwrap();
+
+    assert!(decode_body(Some("gzip"), &body, 1024 * 1024).is_err());
+}
+
+#[test]
+fn decode_unsupported() {
+    assert!(decode_body(Some("compress"), b"body", 1024).is_err());
+}
+
+#[tokio::test]
+async fn read_body_size_limit() {
+    let fetcher = Fetcher::new(&FetcherConfig {
+        max_body_size: 1024,
+        ..Default::default()
+    })
+    .unwrap();
+
+    assert_eq!(
+        fetcher
+            .read_body(Response::new(Body::from(vec![b'a'; 1024])))
+            .await
+            .unwrap()
+            .len(),
+        1024
+    );
+    assert!(fetcher
+        .read_body(Response::new(Body::from(vec![b'a'; 1025])))
+        .await
+        .is_err());
+
+    let res = Response::builder()
+        .header("content-length", "4096")
+        .body(Body::from("a"))
+        .unwrap();
+
+    assert!(fetcher.read_body(res).await.is_err());
+}
+
+#[tokio::test]
+async fn read_body_slow_transfer() {
+    let mut fetcher = Fetcher::new(&FetcherConfig {
+        min_transfer_rate: 1024 * 1024,
+        ..Default::default()
+    })
+    .unwrap();
+
+    fetcher.transfer_grace = Duration::from_millis(50);
+
+    let (mut sender, body) = Body::channel();
+
+    tokio::spawn(async move {
+        loop {
+            if sender.send_data("a".into()).await.is_err() {
+                break;
+            }
+
+            sleep(Duration::from_millis(20)).await;
+        }
+    });
+
+    assert!(fetcher.read_body(Response::new(body)).await.is_err());
+}
+
+#[tokio::test]
+async fn read_body_total_timeout() {
+    let fetcher = Fetcher::new(&FetcherConfig {
+        min_transfer_rate: 0,
+        ..Default::default()
+    })
+    .unwrap();
+    let (mut sender, body) = Body::channel();
+
+    tokio::spawn(async move {
+        while sender.send_data("a".into()).await.is_ok() {
+            sleep(Duration::from_millis(10)).await;
+        }
+    });
+
+    let mut res = Response::new(body);
+
+    res.extensions_mut()
+        .insert(Deadline(Instant::now() + Duration::from_millis(100)));
+
+    assert!(fetcher.read_body(res).await.is_err());
+}
+
+#[tokio::test]
+async fn read_body_prefix() {
+    let fetcher = Fetcher::new(&FetcherConfig::default()).unwrap();
+    let (mut sender, body) = Body::channel();
+
+    tokio::spawn(async move {
+        // the body never ends, so reading must stop at the limit
+        while sender.send_data(vec![b'a'; 1000].into()).await.is_ok() {}
+    });
+
+    assert_eq!(
+        fetcher
+            .read_body_prefix(Response::new(body), 2500)
+            .await
+            .unwrap(),
+        vec![b'a'; 2500]
+    );
+    assert_eq!(
+        fetcher
+            .read_body_prefix(Response::new(Body::from("abc")), 2500)
+            .await
+            .unwrap(),
+        b"abc"
+    );
+}
@@ -0,0 +1,270 @@
+use crate::fetcher::Fetcher;
+use anyhow::{anyhow, Result};
+use hyper::Method;
+use image::{imageops::FilterType, GenericImageView, ImageFormat, ImageOutputFormat};
+use log::{info, warn};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    io::{Cursor, ErrorKind},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tokio::{
+    fs, task,
+    time::{sleep, Duration},
+};
+use uuid::Uuid;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ImageCacheConfig {
+    pub directory: String,
+    /// largest edge length, which can be requested
+    pub max_size: u32,
+    /// time after which the original image is fetched again
+    pub max_age_secs: u64,
+    /// time after which cached files, which were not refreshed, are removed
+    pub cleanup_age_secs: u64,
+    pub cleanup_interval_secs: u64,
+}
+
+impl Default for ImageCacheConfig {
+    fn default() -> Self {
+        Self {
+            directory: String::from("image-cache"),
+            max_size: 1024,
+            max_age_secs: 7 * 24 * 60 * 60,
+            cleanup_age_secs: 30 * 24 * 60 * 60,
+            cleanup_interval_secs: 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CachedImage {
+    pub data: Vec<u8>,
+    pub content_type: &'static str,
+    /// hash of the original image and the size; changes whenever the image does
+    pub etag: String,
+}
+
+/// Caches images on disk.
+///
+/// Originals are stored under the hash of their url and refreshed after `max_age`; resized
+/// copies are stored under the hash of the original content, so they are shared between urls
+/// and invalidated by changes of the image itself. Files, which were not written for
+/// `cleanup_age`, are removed; resized copies still in use are simply created again.
+#[derive(Debug, Clone)]
+pub struct ImageCache {
+    directory: PathBuf,
+    max_size: u32,
+    max_age: Duration,
+    cleanup_age: Duration,
+    cleanup_interval: Duration,
+}
+
+impl ImageCache {
+    pub fn new(config: &ImageCacheConfig) -> Self {
+        Self {
+            directory: PathBuf::from(&config.directory),
+            max_size: config.max_size,
+            max_age: Duration::from_secs(config.max_age_secs),
+            cleanup_age: Duration::from_secs(config.cleanup_age_secs),
+            cleanup_interval: Duration::from_secs(config.cleanup_interval_secs),
+        }
+    }
+
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    /// Checks whether an image can be requested in the size; `None` requests the original.
+    pub fn is_valid_size(&self, size: Option<u32>) -> bool {
+        size.is_none_or(|size| size > 0 && size <= self.max_size)
+    }
+
+    pub async fn get(
+        &self,
+        fetcher: &Fetcher,
+        url: &str,
+        size: Option<u32>,
+    ) -> Result<CachedImage> {
+        if !self.is_valid_size(size) {
+            return Err(anyhow!("size must be between 1 and {}", self.max_size));
+        }
+
+        let original = self.get_original(fetcher, url).await?;
+        let format = image::guess_format(&original)?;
+        let content_hash = hash(&original);
+
+        let size = match size {
+            Some(size) => size,
+            None => {
+                return Ok(CachedImage {
+                    content_type: content_type(format),
+                    etag: content_hash,
+                    data: original,
+                })
+            }
+        };
+
+        let path = self.directory.join(format!("{}-{}", content_hash, size));
+        let (data, format) = match fs::read(&path).await {
+            Ok(data) => {
+                let format = image::guess_format(&data)?;
+
+                (data, format)
+            }
+            Err(_) => {
+                let (data, format) =
+                    task::spawn_blocking(move || resize(&original, size)).await??;
+
+                write_atomic(&path, &data).await?;
+
+                (data, format)
+            }
+        };
+
+        Ok(CachedImage {
+            data,
+            content_type: content_type(format),
+            etag: format!("{}-{}", content_hash, size),
+        })
+    }
+
+    async fn get_original(&self, fetcher: &Fetcher, url: &str) -> Result<Vec<u8>> {
+        let path = self.directory.join(hash(url.as_bytes()));
+        let cached = match fs::metadata(&path).await.and_then(|m| m.modified()) {
+            Ok(modified) => SystemTime::now()
+                .duration_since(modified)
+                .map_or(true, |age| age < self.max_age),
+            Err(_) => false,
+        };
+
+        if cached {
+            return Ok(fs::read(&path).await?);
+        }
+
+        let data = match fetcher.request_compressed(url, &Method::GET).await?.0 {
+            Some(res) => fetcher.read_body(res).await?,
+            None => match fs::read(&path).await {
+                // keep serving the outdated image, if the host is not available
+                Ok(data) => return Ok(data),
+                Err(_) => return Err(anyhow!("error requesting image \"{}\"", url)),
+            },
+        };
+
+        image::guess_format(&data).map_err(|_| anyhow!("\"{}\" is not an image", url))?;
+        write_atomic(&path, &data).await?;
+
+        Ok(data)
+    }
+
+    pub async fn cleanup_loop(&self) {
+        loop {
+            match self.cleanup().await {
+                Ok(count) => info!("removed {} files from the image cache", count),
+                Err(e) => warn!("error cleaning up the image cache: {}", e),
+            }
+
+            sleep(self.cleanup_interval).await;
+        }
+    }
+
+    /// Removes the files not written for `cleanup_age` and returns their number.
+    pub async fn cleanup(&self) -> Result<usize> {
+        let mut entries = match fs::read_dir(&self.directory).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let now = SystemTime::now();
+        let mut count = 0;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let modified = entry.metadata().await?.modified()?;
+
+            if now
+                .duration_since(modified)
+                .is_ok_and(|age| age >= self.cleanup_age)
+            {
+                fs::remove_file(entry.path()).await?;
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+}
+
+/// Writes a file under a temporary name first, so that readers never see partial files.
+async fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let directory = path.parent().ok_or_else(|| anyhow!("invalid cache path"))?;
+    let temporary = directory.join(format!(".{}.tmp", Uuid::new_v4()));
+
+    fs::create_dir_all(directory).await?;
+    fs::write(&temporary, data).await?;
+
+    if let Err(e) = fs::rename(&temporary, path).await {
+        let _ = fs::remove_file(&temporary).await;
+
+        return Err(e.into());
+    }
+
+    Ok(())
+}
+
+fn hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn content_type(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Jpeg => "image/jpeg",
+        ImageFormat::Gif => "image/gif",
+        ImageFormat::Png => "image/png",
+        ImageFormat::WebP => "image/webp",
+        ImageFormat::Bmp => "image/bmp",
+        ImageFormat::Ico => "image/x-icon",
+        ImageFormat::Tiff => "image/tiff",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Scales an image down to fit into a square of the given size; smaller images are kept.
+///
+/// JPEGs stay JPEGs, all other formats are converted to PNG to preserve transparency.
+fn resize(original: &[u8], size: u32) -> Result<(Vec<u8>, ImageFormat)> {
+    let format = image::guess_format(original)?;
+    let img = image::load_from_memory_with_format(original, format)?;
+    let (width, height) = img.dimensions();
+
+    if width <= size && height <= size && is_web_format(format) {
+        return Ok((original.to_vec(), format));
+    }
+
+    let resized = match width <= size && height <= size {
+        true => img,
+        false => img.resize(size, size, FilterType::Lanczos3),
+    };
+    let (output_format, format) = match format {
+        ImageFormat::Jpeg => (ImageOutputFormat::Jpeg(85), ImageFormat::Jpeg),
+        _ => (ImageOutputFormat::Png, ImageFormat::Png),
+    };
+    let mut data = Cursor::new(Vec::<u8>::new());
+
+    resized.write_to(&mut data, output_format)?;
+
+    Ok((data.into_inner(), format))
+}
+
+fn is_web_format(format: ImageFormat) -> bool {
+    matches!(
+        format,
+        ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Gif | ImageFormat::WebP
+    )
+}
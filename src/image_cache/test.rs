@@ -0,0 +1,111 @@
+use super::{content_type, hash, resize, write_atomic, ImageCache, ImageCacheConfig};
+use image::{GenericImageView, ImageFormat, ImageOutputFormat, RgbImage, RgbaImage};
+use std::{
+    fs::{self, File},
+    io::Cursor,
+    time::{Duration, SystemTime},
+};
+use uuid::Uuid;
+
+fn cache_config() -> ImageCacheConfig {
+    ImageCacheConfig {
+        directory: std::env::temp_dir()
+            .join(format!("image-cache-{}", Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned(),
+        ..Default::default()
+    }
+}
+
+fn encode(img: image::DynamicImage, format: ImageOutputFormat) -> Vec<u8> {
+    let mut data = Cursor::new(Vec::<u8>::new());
+
+    img.write_to(&mut data, format).unwrap();
+    data.into_inner()
+}
+
+#[test]
+fn resize_keeps_aspect_ratio() {
+    let original = encode(RgbImage::new(400, 200).into(), ImageOutputFormat::Jpeg(90));
+    let (data, format) = resize(&original, 100).unwrap();
+    let resized = image::load_from_memory(&data).unwrap();
+
+    assert_eq!(format, ImageFormat::Jpeg);
+    assert_eq!(resized.dimensions(), (100, 50));
+}
+
+#[test]
+fn resize_converts_to_png() {
+    let original = encode(RgbaImage::new(64, 64).into(), ImageOutputFormat::Bmp);
+    let (data, format) = resize(&original, 32).unwrap();
+
+    assert_eq!(format, ImageFormat::Png);
+    assert_eq!(image::guess_format(&data).unwrap(), ImageFormat::Png);
+    assert_eq!(content_type(format), "image/png");
+}
+
+#[test]
+fn resize_does_not_upscale() {
+    let original = encode(RgbImage::new(50, 40).into(), ImageOutputFormat::Png);
+    let (data, format) = resize(&original, 100).unwrap();
+
+    assert_eq!(format, ImageFormat::Png);
+    assert_eq!(data, original);
+}
+
+#[test]
+fn hash_is_hex_sha256() {
+    assert_eq!(
+        hash(b""),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+}
+
+#[test]
+fn valid_sizes() {
+    let cache = ImageCache::new(&ImageCacheConfig::default());
+
+    assert!(cache.is_valid_size(None));
+    assert!(cache.is_valid_size(Some(1)));
+    assert!(cache.is_valid_size(Some(1024)));
+    assert!(!cache.is_valid_size(Some(0)));
+    assert!(!cache.is_valid_size(Some(1025)));
+}
+
+#[tokio::test]
+async fn write_atomic_replaces_file() {
+    let config = cache_config();
+    let path = std::path::Path::new(&config.directory).join("image");
+
+    write_atomic(&path, b"old").await.unwrap();
+    write_atomic(&path, b"new").await.unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), b"new");
+    assert_eq!(fs::read_dir(&config.directory).unwrap().count(), 1);
+
+    fs::remove_dir_all(&config.directory).unwrap();
+}
+
+#[tokio::test]
+async fn cleanup_removes_old_files() {
+    let config = cache_config();
+    let cache = ImageCache::new(&config);
+    let directory = std::path::Path::new(&config.directory);
+
+    assert_eq!(cache.cleanup().await.unwrap(), 0);
+
+    write_atomic(&directory.join("old"), b"old").await.unwrap();
+    write_atomic(&directory.join("new"), b"new").await.unwrap();
+    File::options()
+        .write(true)
+        .open(directory.join("old"))
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(config.cleanup_age_secs + 60))
+        .unwrap();
+
+    assert_eq!(cache.cleanup().await.unwrap(), 1);
+    assert!(!directory.join("old").exists());
+    assert!(directory.join("new").exists());
+
+    fs::remove_dir_all(directory).unwrap();
+}
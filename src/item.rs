@@ -0,0 +1,196 @@
+use crate::{canonical_url::UrlCanonicalizer, rss_feed::RssItem};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset, Utc};
+use podcast_player_common::item_val::ItemVal;
+use serde::Serialize;
+use std::convert::TryFrom;
+use uuid::Uuid;
+
+#[cfg(test)]
+mod test;
+
+/// An item together with the flags of items kept although incomplete.
+#[derive(Debug, Clone, Serialize)]
+pub struct Item {
+    #[serde(flatten)]
+    pub val: ItemVal,
+    /// the enclosure url without tracking prefixes, if it has any
+    pub canonical_enclosure_url: Option<String>,
+    /// the image of the item or, if it has none, of its channel
+    pub image: Option<String>,
+    pub date_estimated: bool,
+    pub enclosure_missing: bool,
+    /// the enclosure as reported by its server, if it was probed successfully
+    pub probed_size: Option<i64>,
+    pub probed_type: Option<String>,
+    pub probed_url: Option<String>,
+    /// in seconds, derived from the enclosure itself
+    pub duration: Option<i64>,
+}
+
+impl TryFrom<&tokio_postgres::Row> for Item {
+    type Error = anyhow::Error;
+
+    fn try_from(row: &tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            val: ItemVal::try_from(row)?,
+            canonical_enclosure_url: row.try_get("canonical_enclosure_url")?,
+            image: row.try_get("display_image")?,
+            date_estimated: row.try_get("date_estimated")?,
+            enclosure_missing: row.try_get("enclosure_missing")?,
+            probed_size: row.try_get("probed_size")?,
+            probed_type: row.try_get("probed_type")?,
+            probed_url: row.try_get("probed_url")?,
+            duration: row.try_get("duration")?,
+        })
+    }
+}
+
+/// A media file of an item; `main` marks the one also stored in the item itself.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ItemEnclosure {
+    pub id: Uuid,
+    pub item_id: Uuid,
+    pub url: String,
+    pub canonical_url: Option<String>,
+    pub mime_type: String,
+    pub length: i64,
+    pub bitrate: Option<i64>,
+    pub title: Option<String>,
+    pub main: bool,
+    pub update_ts: DateTime<FixedOffset>,
+}
+
+impl ItemEnclosure {
+    pub fn from_rss_item(
+        item_id: &Uuid,
+        item: &RssItem,
+        canonicalizer: &UrlCanonicalizer,
+    ) -> Vec<Self> {
+        let update_ts: DateTime<FixedOffset> = Utc::now().into();
+        let main = item.enclosure.iter().map(|e| Self {
+            id: Uuid::new_v4(),
+            item_id: *item_id,
+            url: e.url.clone(),
+            canonical_url: canonicalizer.canonicalize(&e.url),
+            mime_type: e.mime_type.clone(),
+            length: e.length,
+            bitrate: None,
+            title: None,
+            main: true,
+            update_ts,
+        });
+        let alternates = item.alternate_enclosures.iter().map(|e| Self {
+            id: Uuid::new_v4(),
+            item_id: *item_id,
+            url: e.url.clone(),
+            canonical_url: canonicalizer.canonicalize(&e.url),
+            mime_type: e.mime_type.clone(),
+            length: e.length,
+            bitrate: e.bitrate,
+            title: e.title.clone(),
+            main: false,
+            update_ts,
+        });
+
+        main.chain(alternates).collect()
+    }
+
+    /// Compares everything but id and timestamp.
+    pub fn same_media(&self, other: &Self) -> bool {
+        self.item_id == other.item_id
+            && self.url == other.url
+            && self.canonical_url == other.canonical_url
+            && self.mime_type == other.mime_type
+            && self.length == other.length
+            && self.bitrate == other.bitrate
+            && self.title == other.title
+            && self.main == other.main
+    }
+
+    /// The url to stream from; the canonical one skips tracking redirects.
+    pub fn stream_url(&self, canonical: bool) -> &str {
+        match (canonical, &self.canonical_url) {
+            (true, Some(url)) => url,
+            _ => &self.url,
+        }
+    }
+
+    fn matches_type(&self, mime_type: &str) -> bool {
+        let essence = self.mime_type.split(';').next().unwrap_or("").trim();
+
+        match mime_type.contains('/') {
+            true => essence.eq_ignore_ascii_case(mime_type),
+            false => essence
+                .split('/')
+                .nth(1)
+                .is_some_and(|subtype| subtype.eq_ignore_ascii_case(mime_type)),
+        }
+    }
+}
+
+impl TryFrom<&tokio_postgres::Row> for ItemEnclosure {
+    type Error = anyhow::Error;
+
+    fn try_from(row: &tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            item_id: row.try_get("item_id")?,
+            url: row.try_get("url")?,
+            canonical_url: row.try_get("canonical_url")?,
+            mime_type: row.try_get("mime_type")?,
+            length: row.try_get("length")?,
+            bitrate: row.try_get("bitrate")?,
+            title: row.try_get("title")?,
+            main: row.try_get("main")?,
+            update_ts: row.try_get("update_ts")?,
+        })
+    }
+}
+
+/// Compares the enclosures of an item regardless of their order.
+pub fn enclosures_changed(old: &[ItemEnclosure], new: &[ItemEnclosure]) -> bool {
+    old.len() != new.len() || new.iter().any(|n| !old.iter().any(|o| o.same_media(n)))
+}
+
+/// Picks the variant of an item to stream.
+///
+/// `mime_type` is either a full mime type ("audio/opus") or a subtype ("opus"). `quality` is
+/// "high", "low", or a bitrate in bits per second, for which the closest variant is chosen.
+/// Without quality the main enclosure is preferred.
+pub fn select_enclosure<'a>(
+    enclosures: &'a [ItemEnclosure],
+    mime_type: Option<&str>,
+    quality: Option<&str>,
+) -> Result<Option<&'a ItemEnclosure>> {
+    let candidates = enclosures
+        .iter()
+        .filter(|e| mime_type.is_none_or(|t| e.matches_type(t)))
+        .collect::<Vec<&ItemEnclosure>>();
+    // the length is proportional to the bitrate for the same episode, if bitrates are missing
+    let by_bitrate = candidates.iter().all(|e| e.bitrate.is_some());
+    let rank = |e: &&ItemEnclosure| match by_bitrate {
+        true => e.bitrate.unwrap_or(0),
+        false => e.length,
+    };
+
+    match quality {
+        None => Ok(candidates
+            .iter()
+            .find(|e| e.main)
+            .or(candidates.first())
+            .copied()),
+        Some("high") => Ok(candidates.into_iter().max_by_key(rank)),
+        Some("low") => Ok(candidates.into_iter().min_by_key(rank)),
+        Some(bitrate) => {
+            let bitrate = bitrate
+                .parse::<i64>()
+                .map_err(|_| anyhow!("invalid quality \"{}\"", bitrate))?;
+
+            Ok(candidates
+                .into_iter()
+                .filter(|e| e.bitrate.is_some())
+                .min_by_key(|e| (e.bitrate.unwrap_or(0) - bitrate).abs()))
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use super::{select_enclosure, ItemEnclosure};
+use crate::{
+    canonical_url::{CanonicalUrlConfig, UrlCanonicalizer},
+    rss_feed::RssFeed,
+};
+use std::{convert::TryFrom, fs};
+use uuid::Uuid;
+
+fn enclosures() -> Vec<ItemEnclosure> {
+    let feed =
+        RssFeed::try_from(&*fs::read_to_string("testFiles/alternate_enclosures.xml").unwrap())
+            .unwrap();
+
+    ItemEnclosure::from_rss_item(
+        &Uuid::new_v4(),
+        &feed.channels[0].items[0],
+        &UrlCanonicalizer::new(&CanonicalUrlConfig::default()),
+    )
+}
+
+fn selected_url(mime_type: Option<&str>, quality: Option<&str>) -> Option<String> {
+    select_enclosure(&enclosures(), mime_type, quality)
+        .unwrap()
+        .map(|e| e.url.clone())
+}
+
+#[test]
+fn select_main_by_default() {
+    assert_eq!(
+        selected_url(None, None).as_deref(),
+        Some("https://example.com/episode1.mp3")
+    );
+}
+
+#[test]
+fn select_by_type() {
+    assert_eq!(
+        selected_url(Some("audio/mp4"), None).as_deref(),
+        Some("https://example.com/episode1.m4a")
+    );
+    assert_eq!(
+        selected_url(Some("OPUS"), None).as_deref(),
+        Some("https://example.com/episode1_low.opus")
+    );
+    assert_eq!(selected_url(Some("video/mp4"), None), None);
+}
+
+#[test]
+fn select_by_quality() {
+    assert_eq!(
+        selected_url(None, Some("high")).as_deref(),
+        Some("https://example.com/episode1.flac")
+    );
+    assert_eq!(
+        selected_url(None, Some("low")).as_deref(),
+        Some("https://example.com/episode1_low.opus")
+    );
+    assert_eq!(
+        selected_url(None, Some("96000")).as_deref(),
+        Some("https://example.com/episode1_low.opus")
+    );
+    assert!(select_enclosure(&enclosures(), None, Some("best")).is_err());
+}
+
+#[test]
+fn changed_enclosures() {
+    let old = enclosures();
+    let mut new = old
+        .iter()
+        .map(|e| ItemEnclosure {
+            id: Uuid::new_v4(),
+            ..e.clone()
+        })
+        .collect::<Vec<ItemEnclosure>>();
+
+    assert!(!super::enclosures_changed(&old, &new));
+
+    new.reverse();
+
+    assert!(!super::enclosures_changed(&old, &new));
+
+    new[1].length += 1;
+
+    assert!(super::enclosures_changed(&old, &new));
+    assert!(super::enclosures_changed(&old, &new[1..]));
+}
+
+#[test]
+fn stream_canonical_url() {
+    let mut enclosure = enclosures().remove(0);
+
+    enclosure.url = String::from("https://chtbl.com/track/1/example.com/episode1.mp3");
+    enclosure.canonical_url = Some(String::from("https://example.com/episode1.mp3"));
+
+    assert_eq!(enclosure.stream_url(false), enclosure.url);
+    assert_eq!(
+        enclosure.stream_url(true),
+        "https://example.com/episode1.mp3"
+    );
+
+    enclosure.canonical_url = None;
+
+    assert_eq!(enclosure.stream_url(true), enclosure.url);
+}
@@ -1,4 +1,19 @@
+pub mod canonical_url;
+pub mod change_events;
+pub mod channel;
+pub mod charset;
+pub mod duration_probe;
+pub mod enclosure_probe;
+pub mod feed_health;
+pub mod feed_schedule;
 pub mod fetcher;
+pub mod image_cache;
+pub mod item;
+pub mod playlist;
 pub mod repo;
 pub mod rss_feed;
+pub mod sync;
+pub mod sync_token;
+pub mod tombstone;
 pub mod updater;
+pub mod validation;
@@ -0,0 +1,116 @@
+use crate::item::Item;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, convert::TryFrom};
+use uuid::Uuid;
+
+#[cfg(test)]
+mod test;
+
+/// An ordered list of items of a user, e.g. the episodes to play next.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Playlist {
+    pub id: Uuid,
+    pub user_id: String,
+    pub title: String,
+    pub item_ids: Vec<Uuid>,
+    pub update_ts: DateTime<FixedOffset>,
+}
+
+impl Playlist {
+    pub fn new(user_id: &str, title: &str) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            user_id: String::from(user_id),
+            title: String::from(title),
+            item_ids: Vec::new(),
+            update_ts: Utc::now().into(),
+        }
+    }
+
+    /// Appends the items, which are not in the playlist yet.
+    pub fn append(&mut self, item_ids: &[Uuid]) {
+        for id in item_ids {
+            if !self.item_ids.contains(id) {
+                self.item_ids.push(*id);
+            }
+        }
+
+        self.update_ts = Utc::now().into();
+    }
+
+    /// Puts the items in the given order, which must contain each item of the playlist once.
+    pub fn reorder(&mut self, item_ids: &[Uuid]) -> Result<()> {
+        let current = self.item_ids.iter().collect::<HashSet<_>>();
+        let reordered = item_ids.iter().collect::<HashSet<_>>();
+
+        if item_ids.len() != self.item_ids.len() || current != reordered {
+            return Err(anyhow!(
+                "the new order must contain exactly the items of the playlist"
+            ));
+        }
+
+        self.item_ids = item_ids.to_vec();
+        self.update_ts = Utc::now().into();
+
+        Ok(())
+    }
+
+    /// Removes an item and returns whether it was in the playlist.
+    pub fn remove(&mut self, item_id: &Uuid) -> bool {
+        let len = self.item_ids.len();
+
+        self.item_ids.retain(|id| id != item_id);
+
+        if self.item_ids.len() == len {
+            return false;
+        }
+
+        self.update_ts = Utc::now().into();
+
+        true
+    }
+}
+
+impl TryFrom<&tokio_postgres::Row> for Playlist {
+    type Error = anyhow::Error;
+
+    fn try_from(row: &tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            user_id: row.try_get("user_id")?,
+            title: row.try_get("title")?,
+            item_ids: row.try_get("item_ids")?,
+            update_ts: row.try_get("update_ts")?,
+        })
+    }
+}
+
+/// Outcome of a change of a playlist.
+#[derive(Debug)]
+pub enum PlaylistUpdate {
+    Updated(Playlist),
+    /// the change does not apply, e.g. an item to be removed is not in the playlist
+    Rejected,
+    NotFound,
+}
+
+/// A playlist together with its items in order.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistDetails {
+    #[serde(flatten)]
+    pub playlist: Playlist,
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreatePlaylistRequest {
+    pub title: String,
+}
+
+/// Items to append to a playlist or the new order of its items.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaylistItemsRequest {
+    pub item_ids: Vec<Uuid>,
+}
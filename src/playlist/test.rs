@@ -0,0 +1,54 @@
+use super::Playlist;
+use uuid::Uuid;
+
+fn ids(count: usize) -> Vec<Uuid> {
+    (0..count).map(|_| Uuid::new_v4()).collect()
+}
+
+#[test]
+fn append() {
+    let items = ids(3);
+    let mut playlist = Playlist::new("user", "up next");
+
+    playlist.append(&items[..2]);
+    playlist.append(&[items[2], items[0]]);
+
+    assert_eq!(playlist.item_ids, items);
+}
+
+#[test]
+fn reorder() {
+    let items = ids(3);
+    let mut playlist = Playlist::new("user", "up next");
+
+    playlist.append(&items);
+    playlist.reorder(&[items[2], items[0], items[1]]).unwrap();
+
+    assert_eq!(playlist.item_ids, vec![items[2], items[0], items[1]]);
+}
+
+#[test]
+fn reorder_different_items() {
+    let items = ids(3);
+    let mut playlist = Playlist::new("user", "up next");
+
+    playlist.append(&items[..2]);
+
+    assert!(playlist.reorder(&[items[1]]).is_err());
+    assert!(playlist.reorder(&[items[1], items[2]]).is_err());
+    assert!(playlist.reorder(&[items[1], items[1]]).is_err());
+    assert!(playlist.reorder(&[items[1], items[0], items[0]]).is_err());
+    assert_eq!(playlist.item_ids, items[..2].to_vec());
+}
+
+#[test]
+fn remove() {
+    let items = ids(3);
+    let mut playlist = Playlist::new("user", "up next");
+
+    playlist.append(&items);
+
+    assert!(playlist.remove(&items[1]));
+    assert!(!playlist.remove(&items[1]));
+    assert_eq!(playlist.item_ids, vec![items[0], items[2]]);
+}
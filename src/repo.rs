@@ -1,9 +1,21 @@
+use crate::{
+    channel::Channel,
+    enclosure_probe::EnclosureProbe,
+    feed_health::FeedFetchLog,
+    feed_schedule::FeedSchedule,
+    item::{Item, ItemEnclosure},
+    playlist::{Playlist, PlaylistUpdate},
+    rss_feed::{Diagnostic, RssChannelMetadata, RssItem},
+    sync::{ChannelMeta, ItemMeta, SyncRequest, SyncResponse},
+    sync_token::{ChangeFilter, SyncToken},
+    tombstone::Tombstone,
+};
 use anyhow::Result;
 use bb8_postgres::{bb8::Pool, PostgresConnectionManager};
-use chrono::{DateTime, FixedOffset};
+use chrono::{DateTime, FixedOffset, Utc};
 use podcast_player_common::{channel_val::ChannelVal, item_val::ItemVal, FeedUrl};
-use std::{convert::TryFrom, str};
-use tokio_postgres::NoTls;
+use std::{convert::TryFrom, str, str::FromStr};
+use tokio_postgres::{error::SqlState, NoTls};
 use uuid::Uuid;
 
 #[derive(Debug, Clone)]
@@ -19,40 +31,59 @@ impl Repo {
         Ok(Repo { pool })
     }
 
-    pub async fn get_objects<T>(&self, update_ts: Option<&str>) -> Result<Vec<T>>
+    /// Token for the changes visible to queries issued after it; to be taken before listing.
+    pub async fn get_sync_token(&self) -> Result<SyncToken> {
+        let row = self
+            .pool
+            .get()
+            .await?
+            .query_one(
+                "SELECT pg_snapshot_xmin(pg_current_snapshot())::text::bigint AS token",
+                &[],
+            )
+            .await?;
+
+        Ok(SyncToken::from_transaction_id(row.try_get("token")?))
+    }
+
+    pub async fn get_objects<T>(&self, filter: &ChangeFilter) -> Result<Vec<T>>
     where
         T: for<'a> std::convert::TryFrom<&'a tokio_postgres::Row> + podcast_player_common::DbInfo,
         Result<Vec<T>, anyhow::Error>:
             for<'a> FromIterator<Result<T, <T as TryFrom<&'a tokio_postgres::Row>>::Error>>,
     {
-        match update_ts
-            .map(DateTime::parse_from_rfc3339)
-            .transpose()?
-            .as_ref()
-        {
-            Some(update) => {
-                self.pool
-                    .get()
-                    .await?
-                    .query(
-                        &*format!("SELECT * FROM {} WHERE update_ts > $1", T::table_name()),
-                        &[update],
-                    )
-                    .await?
-            }
-            None => {
-                self.pool
-                    .get()
-                    .await?
-                    .query(&*format!("SELECT * FROM {}", T::table_name()), &[])
-                    .await?
-            }
-        }
+        self.pool
+            .get()
+            .await?
+            .query(
+                &*format!("SELECT * FROM {} WHERE ($1::timestamptz IS NULL OR update_ts > $1) AND ($2::bigint IS NULL OR change_xid >= $2::bigint::text::xid8)", T::table_name()),
+                &[&filter.since, &filter.transaction_id()],
+            )
+            .await?
         .iter()
         .map(T::try_from)
         .collect()
     }
 
+    /// Tombstones of the objects of a type ("feed", "channel", or "item") deleted after a change.
+    pub async fn get_tombstones(
+        &self,
+        object_type: &str,
+        filter: &ChangeFilter,
+    ) -> Result<Vec<Tombstone>> {
+        self.pool
+            .get()
+            .await?
+            .query(
+                "SELECT * FROM tombstone WHERE object_type = $1 AND ($2::timestamptz IS NULL OR delete_ts > $2) AND ($3::bigint IS NULL OR change_xid >= $3::bigint::text::xid8)",
+                &[&object_type, &filter.since, &filter.transaction_id()],
+            )
+            .await?
+            .iter()
+            .map(Tombstone::try_from)
+            .collect()
+    }
+
     pub async fn update_feed_url(&self, feed_url: &FeedUrl) -> Result<FeedUrl> {
         let rows = self
             .pool.get().await?
@@ -92,6 +123,65 @@ impl Repo {
             .collect()
     }
 
+    pub async fn set_feed_url_error(&self, id: &Uuid, error: Option<&str>) -> Result<()> {
+        self.pool
+            .get()
+            .await?
+            .execute("UPDATE feed_url SET error=$1 WHERE id=$2", &[&error, id])
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_preferred_url_id_by_feed_id(&self, feed_id: &Uuid) -> Result<Option<Uuid>> {
+        let rows = self
+            .pool
+            .get()
+            .await?
+            .query(
+                "SELECT id FROM feed_url WHERE feed_id=$1 AND preferred",
+                &[feed_id],
+            )
+            .await?;
+
+        match rows.len() {
+            0 => Ok(None),
+            1 => Ok(Some(rows[0].try_get("id")?)),
+            _ => Err(anyhow::Error::msg("more than one preferred url found")),
+        }
+    }
+
+    /// Marks `new_id` as the preferred url of the feed; `old_id` is recorded as superseded by it.
+    pub async fn promote_feed_url(
+        &self,
+        feed_id: &Uuid,
+        old_id: &Uuid,
+        new_id: &Uuid,
+    ) -> Result<()> {
+        self.pool.get().await?
+            .execute(
+                "UPDATE feed_url SET preferred=(id=$3), superseded_by=CASE WHEN id=$2 THEN $3 WHEN id=$3 THEN NULL ELSE superseded_by END, update_ts=$4 WHERE feed_id=$1 AND (id=$2 OR id=$3 OR preferred)",
+                &[feed_id, old_id, new_id, &DateTime::<FixedOffset>::from(Utc::now())],
+            ).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_channel_by_id(&self, id: &Uuid) -> Result<Option<ChannelVal>> {
+        let rows = self
+            .pool
+            .get()
+            .await?
+            .query("SELECT * FROM channel_val WHERE id = $1", &[id])
+            .await?;
+
+        match rows.len() {
+            0 => Ok(None),
+            1 => Ok(Some(ChannelVal::try_from(&rows[0])?)),
+            _ => Err(anyhow::Error::msg("more than one row found")),
+        }
+    }
+
     pub async fn get_channel_by_title_feed_id(
         &self,
         title: &str,
@@ -138,6 +228,35 @@ impl Repo {
         }
     }
 
+    pub async fn get_channels(&self, filter: &ChangeFilter) -> Result<Vec<Channel>> {
+        self.pool
+            .get()
+            .await?
+            .query(
+                "SELECT * FROM channel_val WHERE ($1::timestamptz IS NULL OR update_ts > $1) AND ($2::bigint IS NULL OR change_xid >= $2::bigint::text::xid8)",
+                &[&filter.since, &filter.transaction_id()],
+            )
+            .await?
+        .iter()
+        .map(Channel::try_from)
+        .collect()
+    }
+
+    /// Stores the metadata of a channel; the row is only touched if the metadata changed.
+    pub async fn update_channel_metadata(
+        &self,
+        id: &Uuid,
+        metadata: &RssChannelMetadata,
+    ) -> Result<()> {
+        self.pool.get().await?
+            .execute(
+                "UPDATE channel_val SET author=$2, language=$3, categories=$4, link=$5, copyright=$6, owner_name=$7, owner_email=$8, show_type=$9 WHERE id=$1 AND (author, language, categories, link, copyright, owner_name, owner_email, show_type) IS DISTINCT FROM ($2, $3, $4, $5, $6, $7, $8, $9)",
+                &[id, &metadata.author, &metadata.language, &metadata.categories, &metadata.link, &metadata.copyright, &metadata.owner_name, &metadata.owner_email, &metadata.show_type],
+            ).await?;
+
+        Ok(())
+    }
+
     pub async fn get_item_by_id(&self, id: &Uuid) -> Result<ItemVal> {
         let rows = self
             .pool
@@ -153,6 +272,24 @@ impl Repo {
         }
     }
 
+    pub async fn get_item_canonical_enclosure_url(&self, id: &Uuid) -> Result<Option<String>> {
+        let rows = self
+            .pool
+            .get()
+            .await?
+            .query(
+                "SELECT canonical_enclosure_url FROM item_val WHERE id = $1",
+                &[id],
+            )
+            .await?;
+
+        match rows.len() {
+            0 => Err(anyhow::Error::msg("item not found")),
+            1 => Ok(rows[0].try_get("canonical_enclosure_url")?),
+            _ => Err(anyhow::Error::msg("more than one row found")),
+        }
+    }
+
     pub async fn get_item_by_title_date_channel_id(
         &self,
         title: &str,
@@ -176,29 +313,539 @@ impl Repo {
         }
     }
 
-    pub async fn create_item(
+    /// Finds an item kept without date by its title, as its estimated date is not stable.
+    pub async fn get_estimated_item_by_title_channel_id(
         &self,
         title: &str,
+        channel_id: &Uuid,
+    ) -> Result<Option<ItemVal>> {
+        let rows = self
+            .pool
+            .get()
+            .await?
+            .query(
+                "SELECT * FROM item_val WHERE title=$1 AND channel_id=$2 AND date_estimated",
+                &[&title, channel_id],
+            )
+            .await?;
+
+        match rows.len() {
+            0 => Ok(None),
+            1 => Ok(Some(ItemVal::try_from(&rows[0])?)),
+            _ => Err(anyhow::Error::msg("more than one row found")),
+        }
+    }
+
+    pub async fn get_items(&self, filter: &ChangeFilter) -> Result<Vec<Item>> {
+        self.pool
+            .get()
+            .await?
+            .query(
+                // the channel image is the fallback for the image of its items
+                "SELECT item_val.*, COALESCE(item_val.image, channel_val.image) AS display_image FROM item_val JOIN channel_val ON channel_val.id = item_val.channel_id WHERE ($1::timestamptz IS NULL OR item_val.update_ts > $1 OR channel_val.update_ts > $1) AND ($2::bigint IS NULL OR item_val.change_xid >= $2::bigint::text::xid8 OR channel_val.change_xid >= $2::bigint::text::xid8)",
+                &[&filter.since, &filter.transaction_id()],
+            )
+            .await?
+        .iter()
+        .map(Item::try_from)
+        .collect()
+    }
+
+    /// Creates an item; a missing date has to be replaced by an estimate.
+    pub async fn create_item(
+        &self,
+        item: &RssItem,
         date: &DateTime<FixedOffset>,
-        enclosure_type: &str,
-        enclosure_url: &str,
         channel_id: &Uuid,
-        size: i64,
     ) -> Result<ItemVal> {
-        let rows = self.pool.get().await?.query("INSERT INTO item_val (id, title, date, enclosure_type, enclosure_url, channel_id, size) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *", &[&Uuid::new_v4(), &title, date, &enclosure_type, &enclosure_url, channel_id, &size]).await?;
+        let (enclosure_type, enclosure_url, size) = match &item.enclosure {
+            Some(e) => (&*e.mime_type, &*e.url, e.length),
+            None => ("", "", 0),
+        };
+        let rows = self.pool.get().await?.query("INSERT INTO item_val (id, title, date, enclosure_type, enclosure_url, channel_id, size, image, date_estimated, enclosure_missing) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING *", &[&Uuid::new_v4(), &item.title, date, &enclosure_type, &enclosure_url, channel_id, &size, &item.image, &item.date.is_none(), &item.enclosure.is_none()]).await?;
 
         match rows.len() {
             1 => Ok(ItemVal::try_from(&rows[0])?),
-            _ => Err(anyhow::Error::msg("error creating channel")),
+            _ => Err(anyhow::Error::msg("error creating item")),
         }
     }
 
     pub async fn update_item(&self, item: &ItemVal) -> Result<ItemVal> {
-        let rows = self.pool.get().await?.query("UPDATE items SET title=$1, date=$2, enclosure_type=$3, enclosure_url=$4, channel_id=$5 size=$6 WHERE id=$7 RETURNING *", &[&item.title, &item.date, &item.enclosure_type, &item.enclosure_url, &item.channel_id, &item.size, &item.id]).await?;
+        let rows = self.pool.get().await?.query("UPDATE item_val SET title=$1, date=$2, enclosure_type=$3, enclosure_url=$4, channel_id=$5, size=$6, enclosure_missing=($4 = ''), probe_ts=CASE WHEN enclosure_url = $4 THEN probe_ts END, duration=CASE WHEN enclosure_url = $4 THEN duration END, duration_probe_ts=CASE WHEN enclosure_url = $4 THEN duration_probe_ts END WHERE id=$7 RETURNING *", &[&item.title, &item.date, &item.enclosure_type, &item.enclosure_url, &item.channel_id, &item.size, &item.id]).await?;
 
         match rows.len() {
             1 => Ok(ItemVal::try_from(&rows[0])?),
-            _ => Err(anyhow::Error::msg("error updating channel")),
+            _ => Err(anyhow::Error::msg("error updating item")),
+        }
+    }
+
+    /// Stores the image of an item; the row is only touched if the image changed.
+    pub async fn update_item_image(&self, id: &Uuid, image: Option<&str>) -> Result<()> {
+        self.pool
+            .get()
+            .await?
+            .execute(
+                "UPDATE item_val SET image=$2 WHERE id=$1 AND image IS DISTINCT FROM $2",
+                &[id, &image],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Items, whose enclosure was never probed or not since the given time; newest first.
+    pub async fn get_items_to_probe(
+        &self,
+        probed_before: &DateTime<FixedOffset>,
+        limit: i64,
+    ) -> Result<Vec<ItemVal>> {
+        self.pool
+            .get()
+            .await?
+            .query("SELECT * FROM item_val WHERE NOT enclosure_missing AND (probe_ts IS NULL OR probe_ts < $1) ORDER BY probe_ts NULLS FIRST, date DESC LIMIT $2", &[probed_before, &limit])
+            .await?
+            .iter()
+            .map(ItemVal::try_from)
+            .collect()
+    }
+
+    /// Records a probe; the item only counts as changed, if the probed values differ.
+    pub async fn update_item_probe(&self, id: &Uuid, probe: &EnclosureProbe) -> Result<()> {
+        self.pool
+            .get()
+            .await?
+            .execute(
+                "UPDATE item_val SET probed_size=$2, probed_type=$3, probed_url=$4, probe_ts=$5 WHERE id=$1",
+                &[id, &probe.size, &probe.mime_type, &probe.url, &DateTime::<FixedOffset>::from(Utc::now())],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Items, whose duration was never probed; newest first.
+    pub async fn get_items_without_duration(&self, limit: i64) -> Result<Vec<ItemVal>> {
+        self.pool
+            .get()
+            .await?
+            .query("SELECT * FROM item_val WHERE NOT enclosure_missing AND duration_probe_ts IS NULL ORDER BY date DESC LIMIT $1", &[&limit])
+            .await?
+            .iter()
+            .map(ItemVal::try_from)
+            .collect()
+    }
+
+    /// Records a duration probe; the item only counts as changed, if the duration differs.
+    pub async fn update_item_duration(&self, id: &Uuid, duration: Option<i64>) -> Result<()> {
+        self.pool
+            .get()
+            .await?
+            .execute(
+                "UPDATE item_val SET duration=$2, duration_probe_ts=$3 WHERE id=$1",
+                &[id, &duration, &DateTime::<FixedOffset>::from(Utc::now())],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_item_canonical_enclosure_url(
+        &self,
+        id: &Uuid,
+        url: Option<&str>,
+    ) -> Result<()> {
+        self.pool
+            .get()
+            .await?
+            .execute(
+                "UPDATE item_val SET canonical_enclosure_url=$2 WHERE id=$1 AND canonical_enclosure_url IS DISTINCT FROM $2",
+                &[id, &url],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Replaces the estimated date of an item by the date now found in the feed.
+    pub async fn set_item_date(&self, id: &Uuid, date: &DateTime<FixedOffset>) -> Result<()> {
+        self.pool
+            .get()
+            .await?
+            .execute(
+                "UPDATE item_val SET date=$1, date_estimated=false WHERE id=$2",
+                &[date, id],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_item_enclosures_by_item_id(
+        &self,
+        item_id: &Uuid,
+    ) -> Result<Vec<ItemEnclosure>> {
+        self.pool
+            .get()
+            .await?
+            .query(
+                "SELECT * FROM item_enclosure WHERE item_id=$1 ORDER BY main DESC, bitrate, length",
+                &[item_id],
+            )
+            .await?
+            .iter()
+            .map(ItemEnclosure::try_from)
+            .collect()
+    }
+
+    pub async fn replace_item_enclosures(
+        &self,
+        item_id: &Uuid,
+        enclosures: &[ItemEnclosure],
+    ) -> Result<()> {
+        let mut connection = self.pool.get().await?;
+        let transaction = connection.transaction().await?;
+
+        transaction
+            .execute("DELETE FROM item_enclosure WHERE item_id=$1", &[item_id])
+            .await?;
+
+        for e in enclosures {
+            transaction.execute("INSERT INTO item_enclosure (id, item_id, url, canonical_url, mime_type, length, bitrate, title, main, update_ts) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)", &[&e.id, &e.item_id, &e.url, &e.canonical_url, &e.mime_type, &e.length, &e.bitrate, &e.title, &e.main, &e.update_ts]).await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Applies the changes of a client and returns the metadata of the user changed since its last
+    /// sync.
+    pub async fn sync_meta(&self, user_id: &str, request: &SyncRequest) -> Result<SyncResponse> {
+        let since = request
+            .token
+            .as_deref()
+            .map(SyncToken::from_str)
+            .transpose()?
+            .map(|t| t.transaction_id());
+        let mut connection = self.pool.get().await?;
+        let transaction = connection.transaction().await?;
+        let token = SyncToken::from_transaction_id(
+            transaction
+                .query_one(
+                    "SELECT pg_snapshot_xmin(pg_current_snapshot())::text::bigint AS token",
+                    &[],
+                )
+                .await?
+                .try_get("token")?,
+        );
+
+        for client in &request.channel_metas {
+            let server = transaction
+                .query_opt(
+                    "SELECT * FROM channel_meta WHERE user_id=$1 AND channel_id=$2 FOR UPDATE",
+                    &[&user_id, &client.channel_id],
+                )
+                .await?
+                .as_ref()
+                .map(ChannelMeta::try_from)
+                .transpose()?;
+
+            if let Some(m) = client.resolve(server.as_ref()) {
+                transaction.execute("INSERT INTO channel_meta (user_id, id, channel_id, active, synced, volume, playback_rate, update_ts) VALUES ($1, $2, $3, $4, true, $5, $6, $7) ON CONFLICT (user_id, channel_id) DO UPDATE SET active=$4, synced=true, volume=$5, playback_rate=$6, update_ts=$7", &[&user_id, &m.id, &m.channel_id, &m.active, &m.volume, &m.playback_rate, &m.update_ts]).await?;
+            }
+        }
+
+        for client in &request.item_metas {
+            let server = transaction
+                .query_opt("SELECT id, item_id, new, download_status::text AS download_status, playback_time, play_count, update_ts FROM item_meta WHERE user_id=$1 AND item_id=$2 FOR UPDATE", &[&user_id, &client.item_id])
+                .await?
+                .as_ref()
+                .map(ItemMeta::try_from)
+                .transpose()?;
+
+            if let Some(m) = client.resolve(server.as_ref()) {
+                transaction.execute("INSERT INTO item_meta (user_id, id, item_id, new, download_status, playback_time, play_count, synced, update_ts) VALUES ($1, $2, $3, $4, $5::text::download_status, $6, $7, true, $8) ON CONFLICT (user_id, item_id) DO UPDATE SET new=$4, download_status=$5::text::download_status, playback_time=$6, play_count=$7, synced=true, update_ts=$8", &[&user_id, &m.id, &m.item_id, &m.new, &m.download_status.to_string(), &m.playback_time, &m.play_count, &m.update_ts]).await?;
+            }
+        }
+
+        let channel_metas = transaction
+            .query(
+                "SELECT * FROM channel_meta WHERE user_id=$1 AND ($2::bigint IS NULL OR change_xid >= $2::bigint::text::xid8)",
+                &[&user_id, &since],
+            )
+            .await?
+            .iter()
+            .map(ChannelMeta::try_from)
+            .collect::<Result<Vec<ChannelMeta>>>()?;
+        let item_metas = transaction
+            .query("SELECT id, item_id, new, download_status::text AS download_status, playback_time, play_count, update_ts FROM item_meta WHERE user_id=$1 AND ($2::bigint IS NULL OR change_xid >= $2::bigint::text::xid8)", &[&user_id, &since])
+            .await?
+            .iter()
+            .map(ItemMeta::try_from)
+            .collect::<Result<Vec<ItemMeta>>>()?;
+
+        transaction.commit().await?;
+
+        Ok(SyncResponse {
+            token: token.to_string(),
+            channel_metas,
+            item_metas,
+        })
+    }
+
+    pub async fn get_playlists_by_user_id(&self, user_id: &str) -> Result<Vec<Playlist>> {
+        self.pool
+            .get()
+            .await?
+            .query(
+                "SELECT playlist.*, COALESCE(array_agg(playlist_item.item_id ORDER BY playlist_item.position) FILTER (WHERE playlist_item.item_id IS NOT NULL), '{}') AS item_ids FROM playlist LEFT JOIN playlist_item ON playlist_item.playlist_id = playlist.id WHERE playlist.user_id = $1 GROUP BY playlist.id ORDER BY playlist.title",
+                &[&user_id],
+            )
+            .await?
+            .iter()
+            .map(Playlist::try_from)
+            .collect()
+    }
+
+    /// Returns the playlist, if it exists and belongs to the user.
+    pub async fn get_playlist_by_id(&self, user_id: &str, id: &Uuid) -> Result<Option<Playlist>> {
+        self.pool
+            .get()
+            .await?
+            .query_opt(
+                "SELECT playlist.*, COALESCE(array_agg(playlist_item.item_id ORDER BY playlist_item.position) FILTER (WHERE playlist_item.item_id IS NOT NULL), '{}') AS item_ids FROM playlist LEFT JOIN playlist_item ON playlist_item.playlist_id = playlist.id WHERE playlist.id = $1 AND playlist.user_id = $2 GROUP BY playlist.id",
+                &[id, &user_id],
+            )
+            .await?
+            .as_ref()
+            .map(Playlist::try_from)
+            .transpose()
+    }
+
+    pub async fn get_playlist_items(&self, id: &Uuid) -> Result<Vec<Item>> {
+        self.pool
+            .get()
+            .await?
+            .query(
+                "SELECT item_val.*, COALESCE(item_val.image, channel_val.image) AS display_image FROM playlist_item JOIN item_val ON item_val.id = playlist_item.item_id JOIN channel_val ON channel_val.id = item_val.channel_id WHERE playlist_item.playlist_id = $1 ORDER BY playlist_item.position",
+                &[id],
+            )
+            .await?
+            .iter()
+            .map(Item::try_from)
+            .collect()
+    }
+
+    pub async fn create_playlist(&self, playlist: &Playlist) -> Result<Playlist> {
+        let mut connection = self.pool.get().await?;
+        let transaction = connection.transaction().await?;
+
+        transaction
+            .execute(
+                "INSERT INTO playlist (id, user_id, title, update_ts) VALUES ($1, $2, $3, $4)",
+                &[
+                    &playlist.id,
+                    &playlist.user_id,
+                    &playlist.title,
+                    &playlist.update_ts,
+                ],
+            )
+            .await?;
+        transaction.execute("INSERT INTO playlist_item (playlist_id, item_id, position) SELECT $1, t.item_id, t.position::int FROM unnest($2::uuid[]) WITH ORDINALITY AS t(item_id, position)", &[&playlist.id, &playlist.item_ids]).await?;
+        transaction.commit().await?;
+
+        Ok(playlist.clone())
+    }
+
+    /// Changes the playlist of a user, while it is locked against concurrent changes.
+    ///
+    /// The change is rejected, if `update` returns false or the playlist would contain unknown
+    /// items.
+    pub async fn update_playlist<F>(
+        &self,
+        user_id: &str,
+        id: &Uuid,
+        update: F,
+    ) -> Result<PlaylistUpdate>
+    where
+        F: FnOnce(&mut Playlist) -> bool,
+    {
+        let mut connection = self.pool.get().await?;
+        let transaction = connection.transaction().await?;
+
+        if transaction
+            .query_opt(
+                "SELECT id FROM playlist WHERE id = $1 AND user_id = $2 FOR UPDATE",
+                &[id, &user_id],
+            )
+            .await?
+            .is_none()
+        {
+            return Ok(PlaylistUpdate::NotFound);
+        }
+
+        let mut playlist = Playlist::try_from(&transaction.query_one("SELECT playlist.*, COALESCE(array_agg(playlist_item.item_id ORDER BY playlist_item.position) FILTER (WHERE playlist_item.item_id IS NOT NULL), '{}') AS item_ids FROM playlist LEFT JOIN playlist_item ON playlist_item.playlist_id = playlist.id WHERE playlist.id = $1 GROUP BY playlist.id", &[id]).await?)?;
+
+        if !update(&mut playlist) {
+            return Ok(PlaylistUpdate::Rejected);
+        }
+
+        transaction
+            .execute(
+                "UPDATE playlist SET title = $2, update_ts = $3 WHERE id = $1",
+                &[&playlist.id, &playlist.title, &playlist.update_ts],
+            )
+            .await?;
+        transaction
+            .execute("DELETE FROM playlist_item WHERE playlist_id = $1", &[id])
+            .await?;
+        match transaction.execute("INSERT INTO playlist_item (playlist_id, item_id, position) SELECT $1, t.item_id, t.position::int FROM unnest($2::uuid[]) WITH ORDINALITY AS t(item_id, position)", &[&playlist.id, &playlist.item_ids]).await {
+            Err(e) if e.code() == Some(&SqlState::FOREIGN_KEY_VIOLATION) => {
+                return Ok(PlaylistUpdate::Rejected)
+            }
+            inserted => inserted?,
+        };
+        transaction.commit().await?;
+
+        Ok(PlaylistUpdate::Updated(playlist))
+    }
+
+    /// Deletes the playlist of a user and returns whether it existed.
+    pub async fn delete_playlist(&self, user_id: &str, id: &Uuid) -> Result<bool> {
+        Ok(self
+            .pool
+            .get()
+            .await?
+            .execute(
+                "DELETE FROM playlist WHERE id = $1 AND user_id = $2",
+                &[id, &user_id],
+            )
+            .await?
+            == 1)
+    }
+
+    pub async fn create_feed_fetch_log(&self, log: &FeedFetchLog) -> Result<FeedFetchLog> {
+        let rows = self
+            .pool.get().await?
+            .query(
+                "INSERT INTO feed_fetch_log (id, feed_id, url, status, error, channel_count, item_count, duration_ms, fetch_ts) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *",
+                &[&log.id, &log.feed_id, &log.url, &log.status, &log.error, &log.channel_count, &log.item_count, &log.duration_ms, &log.fetch_ts],
+            ).await?;
+
+        match rows.len() {
+            1 => Ok(FeedFetchLog::try_from(&rows[0])?),
+            _ => Err(anyhow::Error::msg("error creating feed fetch log")),
+        }
+    }
+
+    /// Deletes all but the latest `keep` fetches of a feed together with their diagnostics.
+    pub async fn delete_old_feed_fetch_logs(&self, feed_id: &Uuid, keep: i64) -> Result<()> {
+        self.pool.get().await?
+            .execute(
+                "DELETE FROM feed_fetch_log WHERE feed_id=$1 AND id NOT IN (SELECT id FROM feed_fetch_log WHERE feed_id=$1 ORDER BY fetch_ts DESC LIMIT $2)",
+                &[feed_id, &keep],
+            ).await?;
+
+        Ok(())
+    }
+
+    pub async fn create_feed_fetch_diagnostics(
+        &self,
+        fetch_id: &Uuid,
+        diagnostics: &[Diagnostic],
+    ) -> Result<()> {
+        let mut connection = self.pool.get().await?;
+        let transaction = connection.transaction().await?;
+
+        for (position, d) in diagnostics.iter().enumerate() {
+            transaction
+                .execute(
+                    "INSERT INTO feed_fetch_diagnostic (fetch_id, position, path, severity, message) VALUES ($1, $2, $3, $4, $5)",
+                    &[fetch_id, &(position as i32), &d.path, &d.severity.to_string(), &d.message],
+                )
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn get_feed_fetch_diagnostics_by_fetch_id(
+        &self,
+        fetch_id: &Uuid,
+    ) -> Result<Vec<Diagnostic>> {
+        self.pool
+            .get()
+            .await?
+            .query(
+                "SELECT * FROM feed_fetch_diagnostic WHERE fetch_id=$1 ORDER BY position",
+                &[fetch_id],
+            )
+            .await?
+            .iter()
+            .map(Diagnostic::try_from)
+            .collect()
+    }
+
+    pub async fn get_feed_fetch_logs_by_feed_id(
+        &self,
+        feed_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<FeedFetchLog>> {
+        self.pool
+            .get()
+            .await?
+            .query(
+                "SELECT * FROM feed_fetch_log WHERE feed_id=$1 ORDER BY fetch_ts DESC LIMIT $2",
+                &[feed_id, &limit],
+            )
+            .await?
+            .iter()
+            .map(FeedFetchLog::try_from)
+            .collect()
+    }
+
+    pub async fn get_feed_schedules(&self) -> Result<Vec<FeedSchedule>> {
+        self.pool
+            .get()
+            .await?
+            .query("SELECT * FROM feed_schedule", &[])
+            .await?
+            .iter()
+            .map(FeedSchedule::try_from)
+            .collect()
+    }
+
+    pub async fn get_feed_schedule_by_feed_id(
+        &self,
+        feed_id: &Uuid,
+    ) -> Result<Option<FeedSchedule>> {
+        let rows = self
+            .pool
+            .get()
+            .await?
+            .query("SELECT * FROM feed_schedule WHERE feed_id=$1", &[feed_id])
+            .await?;
+
+        match rows.len() {
+            0 => Ok(None),
+            1 => Ok(Some(FeedSchedule::try_from(&rows[0])?)),
+            _ => Err(anyhow::Error::msg("more than one row found")),
+        }
+    }
+
+    pub async fn upsert_feed_schedule(&self, schedule: &FeedSchedule) -> Result<FeedSchedule> {
+        let rows = self
+            .pool.get().await?
+            .query(
+                "INSERT INTO feed_schedule (feed_id, consecutive_failures, next_fetch_ts, disabled, update_ts) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (feed_id) DO UPDATE SET consecutive_failures=$2, next_fetch_ts=$3, disabled=$4, update_ts=$5 RETURNING *",
+                &[&schedule.feed_id, &schedule.consecutive_failures, &schedule.next_fetch_ts, &schedule.disabled, &schedule.update_ts],
+            ).await?;
+
+        match rows.len() {
+            1 => Ok(FeedSchedule::try_from(&rows[0])?),
+            _ => Err(anyhow::Error::msg("error updating feed schedule")),
         }
     }
 }
@@ -1,61 +1,134 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, FixedOffset};
 use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
+mod date;
+mod diagnostic;
+mod recovery;
 #[cfg(test)]
 mod test;
 
+pub use diagnostic::{Diagnostic, Severity};
+pub use recovery::RecoveryReport;
+
+const ITUNES_NAMESPACE: &str = "http://www.itunes.com/dtds/podcast-1.0.dtd";
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct ParseOptions {
+    /// keep items without date or enclosure instead of dropping them
+    pub keep_incomplete_items: bool,
+}
+
 #[derive(Debug)]
 pub struct RssFeed {
     pub channels: Vec<RssChannel>,
+    pub new_feed_url: Option<String>,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct RssChannel {
     pub title: String,
     pub description: String,
     pub image: Option<String>,
+    pub metadata: RssChannelMetadata,
     pub items: Vec<RssItem>,
 }
 
-#[derive(Debug, PartialEq)]
+/// Categories are paths of nested iTunes categories, e.g. "Technology/Podcasting".
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RssChannelMetadata {
+    pub author: Option<String>,
+    pub language: Option<String>,
+    pub categories: Vec<String>,
+    pub link: Option<String>,
+    pub copyright: Option<String>,
+    pub owner_name: Option<String>,
+    pub owner_email: Option<String>,
+    /// "episodic" or "serial"
+    pub show_type: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
 pub struct RssEnclosure {
     pub url: String,
     pub mime_type: String,
     pub length: i64,
 }
 
-#[derive(Debug, PartialEq)]
+/// Further enclosures of an item and its `podcast:alternateEnclosure`s.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RssAlternateEnclosure {
+    pub url: String,
+    pub mime_type: String,
+    pub length: i64,
+    pub bitrate: Option<i64>,
+    pub title: Option<String>,
+}
+
+/// Date and enclosure are always present, unless incomplete items are kept.
+#[derive(Debug, PartialEq, Serialize)]
 pub struct RssItem {
-    pub date: DateTime<FixedOffset>,
+    pub date: Option<DateTime<FixedOffset>>,
     pub title: String,
-    pub enclosure: RssEnclosure,
+    pub enclosure: Option<RssEnclosure>,
+    pub alternate_enclosures: Vec<RssAlternateEnclosure>,
+    pub image: Option<String>,
 }
 
 impl RssFeed {
-    fn parse_root(root: Node) -> Result<RssFeed> {
+    fn parse_root(root: Node, options: &ParseOptions) -> Result<RssFeed> {
         let mut channels = Vec::<RssChannel>::new();
+        let mut new_feed_url: Option<String> = None;
+        let mut diagnostics = Vec::<Diagnostic>::new();
 
         if root.tag_name().name() == "channel" {
-            channels.push(Self::parse_channel(root)?);
+            new_feed_url = Self::parse_new_feed_url(root);
+            channels.push(Self::parse_channel(root, options, &mut diagnostics)?);
         } else {
             for node in root.children() {
                 match node.tag_name().name() {
-                    "channel" => channels.push(Self::parse_channel(node)?),
+                    "channel" => {
+                        new_feed_url = new_feed_url.or(Self::parse_new_feed_url(node));
+                        channels.push(Self::parse_channel(node, options, &mut diagnostics)?)
+                    }
                     _ => {}
                 }
             }
         }
 
-        Ok(RssFeed { channels })
+        Ok(RssFeed {
+            channels,
+            new_feed_url,
+            diagnostics,
+        })
+    }
+
+    fn parse_new_feed_url(channel: Node) -> Option<String> {
+        channel
+            .children()
+            .find(|node| {
+                node.tag_name().namespace() == Some(ITUNES_NAMESPACE)
+                    && node.tag_name().name() == "new-feed-url"
+            })
+            .and_then(|node| node.text())
+            .map(|e| String::from(e.trim()))
+            .filter(|e| !e.is_empty())
     }
 
-    fn parse_channel(channel: Node) -> Result<RssChannel> {
+    fn parse_channel(
+        channel: Node,
+        options: &ParseOptions,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Result<RssChannel> {
         let mut title: Option<String> = None;
         let mut description: Option<String> = None;
         let mut itunes_description: Option<String> = None;
         let mut image: Option<String> = None;
+        let mut metadata = RssChannelMetadata::default();
         let mut items: Vec<RssItem> = Vec::new();
 
         for node in channel.children() {
@@ -66,15 +139,43 @@ impl RssFeed {
                 (_, "description") => {
                     description = node.text().map(|e| String::from(e.trim()));
                 }
-                (Some("http://www.itunes.com/dtds/podcast-1.0.dtd"), "summary") => {
+                (Some(ITUNES_NAMESPACE), "summary") => {
                     itunes_description = node.text().map(|e| String::from(e.trim()));
                 }
+                (Some(ITUNES_NAMESPACE), "author") => metadata.author = Self::parse_text(node),
+                (Some(ITUNES_NAMESPACE), "category") => {
+                    Self::parse_category(node, "", &mut metadata.categories)
+                }
+                (Some(ITUNES_NAMESPACE), "owner") => {
+                    for child in node.children() {
+                        match (child.tag_name().namespace(), child.tag_name().name()) {
+                            (Some(ITUNES_NAMESPACE), "name") => {
+                                metadata.owner_name = Self::parse_text(child)
+                            }
+                            (Some(ITUNES_NAMESPACE), "email") => {
+                                metadata.owner_email = Self::parse_text(child)
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                (Some(ITUNES_NAMESPACE), "type") => {
+                    metadata.show_type = Self::parse_text(node)
+                        .map(|t| t.to_lowercase())
+                        .filter(|t| t == "episodic" || t == "serial")
+                }
+                (None, "language") => metadata.language = Self::parse_text(node),
+                (None, "link") => metadata.link = Self::parse_text(node),
+                (None, "copyright") => metadata.copyright = Self::parse_text(node),
                 (_, "image") => {
                     image = Self::parse_image(node)?;
                 }
-                (_, "item") => match Self::parse_item(node) {
+                (_, "item") => match Self::parse_item(node, options, diagnostics) {
                     Ok(item) => items.push(item),
-                    Err(e) => log::error!("error parsing item: {}", e),
+                    Err(e) => {
+                        log::error!("error parsing item: {}", e);
+                        diagnostics.push(Diagnostic::error(node, format!("item dropped: {}", e)));
+                    }
                 },
                 _ => {}
             }
@@ -85,12 +186,14 @@ impl RssFeed {
                 title,
                 description,
                 image,
+                metadata,
                 items,
             }),
             (Some(title), Some(description), None) => Ok(RssChannel {
                 title,
                 description,
                 image,
+                metadata,
                 items,
             }),
             _ => Err(anyhow::Error::msg(
@@ -99,6 +202,36 @@ impl RssFeed {
         }
     }
 
+    fn parse_text(node: Node) -> Option<String> {
+        node.text()
+            .map(|t| String::from(t.trim()))
+            .filter(|t| !t.is_empty())
+    }
+
+    /// Collects the path of a category and its subcategories.
+    fn parse_category(category: Node, parent: &str, categories: &mut Vec<String>) {
+        let path = match category.attribute("text").map(|t| t.trim()) {
+            Some(text) if !text.is_empty() && parent.is_empty() => String::from(text),
+            Some(text) if !text.is_empty() => format!("{}/{}", parent, text),
+            _ => return,
+        };
+        let subcategories = category
+            .children()
+            .filter(|c| {
+                c.tag_name().namespace() == Some(ITUNES_NAMESPACE)
+                    && c.tag_name().name() == "category"
+            })
+            .collect::<Vec<Node>>();
+
+        for subcategory in &subcategories {
+            Self::parse_category(*subcategory, &path, categories);
+        }
+
+        if subcategories.is_empty() {
+            categories.push(path);
+        }
+    }
+
     fn parse_image(image: Node) -> Result<Option<String>> {
         let mut image_url: Option<&str> = image.attribute("href");
 
@@ -114,16 +247,59 @@ impl RssFeed {
         Ok(image_url.map(|e| String::from(e)))
     }
 
-    fn parse_item(item: Node) -> Result<RssItem> {
+    fn parse_item(
+        item: Node,
+        options: &ParseOptions,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Result<RssItem> {
         let mut title: Option<&str> = None;
         let mut date: Option<&str> = None;
         let mut enclosure: Option<RssEnclosure> = None;
+        let mut alternate_enclosures = Vec::<RssAlternateEnclosure>::new();
+        let mut image: Option<String> = None;
+        let mut thumbnail: Option<String> = None;
 
         for node in item.children() {
             match node.tag_name().name() {
                 "title" => title = node.text(),
                 "pubDate" => date = node.text(),
-                "enclosure" => enclosure = Some(Self::parse_enclosure(node)?),
+                "image" if node.tag_name().namespace() == Some(ITUNES_NAMESPACE) => {
+                    image = Self::parse_image(node)?
+                }
+                "thumbnail" => {
+                    thumbnail = thumbnail.or(node
+                        .attribute("url")
+                        .map(|u| String::from(u.trim()))
+                        .filter(|u| !u.is_empty()))
+                }
+                // the first enclosure is the main one, later ones are kept as alternatives
+                "enclosure" if enclosure.is_some() => match Self::parse_enclosure(node) {
+                    Ok(e) => alternate_enclosures.push(RssAlternateEnclosure {
+                        url: e.url,
+                        mime_type: e.mime_type,
+                        length: e.length,
+                        bitrate: None,
+                        title: None,
+                    }),
+                    Err(e) => diagnostics.push(Diagnostic::warning(
+                        node,
+                        format!("enclosure ignored: {}", e),
+                    )),
+                },
+                "alternateEnclosure" => match Self::parse_alternate_enclosure(node) {
+                    Ok(e) => alternate_enclosures.push(e),
+                    Err(e) => diagnostics.push(Diagnostic::warning(
+                        node,
+                        format!("alternate enclosure ignored: {}", e),
+                    )),
+                },
+                "enclosure" => match Self::parse_enclosure(node) {
+                    Ok(e) => enclosure = Some(e),
+                    Err(e) if options.keep_incomplete_items => diagnostics.push(
+                        Diagnostic::warning(node, format!("enclosure ignored: {}", e)),
+                    ),
+                    Err(e) => return Err(e),
+                },
                 _ => {}
             }
         }
@@ -135,11 +311,40 @@ impl RssFeed {
             enclosure
         );
 
+        if options.keep_incomplete_items {
+            let title = title.ok_or(anyhow::anyhow!("could not find title for item"))?;
+            let date = match date.map(Self::parse_date) {
+                Some(Ok(d)) => Some(d),
+                Some(Err(e)) => {
+                    diagnostics.push(Diagnostic::warning(item, format!("date estimated: {}", e)));
+                    None
+                }
+                None => {
+                    diagnostics.push(Diagnostic::warning(item, "date estimated: missing pubDate"));
+                    None
+                }
+            };
+
+            if enclosure.is_none() {
+                diagnostics.push(Diagnostic::warning(item, "item kept without enclosure"));
+            }
+
+            return Ok(RssItem {
+                title: String::from(title),
+                date,
+                enclosure,
+                alternate_enclosures,
+                image: image.or(thumbnail),
+            });
+        }
+
         match (title, date, enclosure) {
             (Some(title), Some(date), Some(enclosure)) => Ok(RssItem {
                 title: String::from(title),
-                date: Self::parse_date(date)?,
-                enclosure,
+                date: Some(Self::parse_date(date)?),
+                enclosure: Some(enclosure),
+                alternate_enclosures,
+                image: image.or(thumbnail),
             }),
             (None, _, _) => Err(anyhow::anyhow!("could not find title for item")),
             (_, None, _) => Err(anyhow::anyhow!("could not find date for item")),
@@ -165,13 +370,67 @@ impl RssFeed {
         }
     }
 
+    fn parse_alternate_enclosure(enclosure: Node) -> Result<RssAlternateEnclosure> {
+        // prefer sources, which can be streamed over http, to torrents or ipfs
+        let url = enclosure
+            .children()
+            .filter(|n| n.tag_name().name() == "source")
+            .filter_map(|n| n.attribute("uri"))
+            .find(|uri| uri.starts_with("http://") || uri.starts_with("https://"))
+            .ok_or(anyhow::anyhow!("could not find http source"))?;
+        let mime_type = enclosure
+            .attribute("type")
+            .ok_or(anyhow::anyhow!("could not find type"))?;
+
+        Ok(RssAlternateEnclosure {
+            url: String::from(url),
+            mime_type: String::from(mime_type),
+            length: enclosure
+                .attribute("length")
+                .map(|l| l.trim().parse::<i64>())
+                .transpose()?
+                .unwrap_or(0),
+            bitrate: enclosure
+                .attribute("bitrate")
+                .map(|b| b.trim().parse::<f64>())
+                .transpose()?
+                .map(|b| b.round() as i64),
+            title: enclosure
+                .attribute("title")
+                .filter(|t| !t.is_empty())
+                .map(String::from),
+        })
+    }
+
     fn parse_date(date: &str) -> Result<DateTime<FixedOffset>> {
-        let res = DateTime::parse_from_rfc2822(date).context("error parsing date");
+        date::parse_date(date)
+    }
+}
+
+impl RssFeed {
+    /// Parses a feed like `try_from`, but tries to repair documents, which are not well-formed.
+    pub fn parse_lenient(value: &str, options: &ParseOptions) -> Result<(RssFeed, RecoveryReport)> {
+        match Document::parse(value) {
+            Ok(doc) => Ok((
+                Self::parse_root(doc.root_element(), options)?,
+                RecoveryReport::default(),
+            )),
+            Err(e) => {
+                let (recovered, report) = recovery::recover(value);
+                let doc = Document::parse(&recovered)
+                    .with_context(|| format!("could not recover from \"{}\"", e))?;
+                let mut feed = Self::parse_root(doc.root_element(), options)?;
+
+                feed.diagnostics.insert(
+                    0,
+                    Diagnostic::warning(
+                        doc.root_element(),
+                        format!("feed is not well-formed ({}); repaired: {:?}", e, report),
+                    ),
+                );
 
-        match res {
-            Err(_) => DateTime::parse_from_str(date, "%d %b %Y %k:%M:%S%.3f %z")
-                .context("error parsing date"),
-            _ => res,
+                Ok((feed, report))
+            }
         }
     }
 }
@@ -182,6 +441,6 @@ impl TryFrom<&str> for RssFeed {
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         let doc = Document::parse(value)?;
 
-        Self::parse_root(doc.root_element())
+        Self::parse_root(doc.root_element(), &ParseOptions::default())
     }
 }
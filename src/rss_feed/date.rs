@@ -0,0 +1,345 @@
+//! Parsing of the dates found in feeds.
+//!
+//! Dates are tried in the following order:
+//! 1. RFC 2822 (the format required by RSS)
+//! 2. RFC 3339
+//! 3. after normalization (dropping the weekday, translating localized month names, and
+//!    replacing timezone abbreviations by offsets) a list of common formats with offset
+//! 4. the same formats without offset, which are interpreted as UTC
+//! 5. dates without time, which are interpreted as midnight UTC
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+
+const FORMATS_WITH_OFFSET: &[&str] = &[
+    "%d %b %Y %H:%M:%S%.f %z",
+    "%d %b %Y %H:%M %z",
+    "%b %d %Y %H:%M:%S %z",
+    "%Y-%m-%dT%H:%M:%S%.f%z",
+    "%Y-%m-%dT%H:%M%z",
+    "%Y-%m-%d %H:%M:%S%.f %z",
+    "%Y-%m-%d %H:%M:%S%.f%z",
+    "%Y-%m-%d %H:%M %z",
+];
+
+const FORMATS_WITHOUT_OFFSET: &[&str] = &[
+    "%d %b %Y %H:%M:%S%.f",
+    "%d %b %Y %H:%M",
+    "%b %d %Y %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+];
+
+const DATE_FORMATS: &[&str] = &["%d %b %Y", "%b %d %Y", "%Y-%m-%d"];
+
+// offsets in minutes; "IST" is taken to be India Standard Time
+const TIMEZONES: &[(&str, i32)] = &[
+    ("Z", 0),
+    ("UT", 0),
+    ("UTC", 0),
+    ("GMT", 0),
+    ("WET", 0),
+    ("WEST", 60),
+    ("BST", 60),
+    ("CET", 60),
+    ("MEZ", 60),
+    ("CEST", 120),
+    ("MESZ", 120),
+    ("EET", 120),
+    ("EEST", 180),
+    ("MSK", 180),
+    ("IST", 330),
+    ("SGT", 480),
+    ("HKT", 480),
+    ("AWST", 480),
+    ("JST", 540),
+    ("KST", 540),
+    ("ACST", 570),
+    ("ACDT", 630),
+    ("AEST", 600),
+    ("AEDT", 660),
+    ("NZST", 720),
+    ("NZDT", 780),
+    ("NST", -210),
+    ("NDT", -150),
+    ("AST", -240),
+    ("ADT", -180),
+    ("EST", -300),
+    ("EDT", -240),
+    ("CST", -360),
+    ("CDT", -300),
+    ("MST", -420),
+    ("MDT", -360),
+    ("PST", -480),
+    ("PDT", -420),
+    ("AKST", -540),
+    ("AKDT", -480),
+    ("HST", -600),
+    ("HAST", -600),
+    ("HADT", -540),
+    ("SST", -660),
+    ("CHST", 600),
+    ("ET", -300),
+];
+
+// full and localized month names (lower case, without trailing dots) and their English
+// abbreviations
+const MONTHS: &[(&str, &str)] = &[
+    // English
+    ("january", "Jan"),
+    ("february", "Feb"),
+    ("march", "Mar"),
+    ("april", "Apr"),
+    ("june", "Jun"),
+    ("july", "Jul"),
+    ("august", "Aug"),
+    ("september", "Sep"),
+    ("october", "Oct"),
+    ("november", "Nov"),
+    ("december", "Dec"),
+    // German
+    ("januar", "Jan"),
+    ("jänner", "Jan"),
+    ("februar", "Feb"),
+    ("märz", "Mar"),
+    ("mär", "Mar"),
+    ("mrz", "Mar"),
+    ("mai", "May"),
+    ("juni", "Jun"),
+    ("juli", "Jul"),
+    ("oktober", "Oct"),
+    ("okt", "Oct"),
+    ("dezember", "Dec"),
+    ("dez", "Dec"),
+    // French
+    ("janvier", "Jan"),
+    ("janv", "Jan"),
+    ("février", "Feb"),
+    ("févr", "Feb"),
+    ("fév", "Feb"),
+    ("mars", "Mar"),
+    ("avril", "Apr"),
+    ("avr", "Apr"),
+    ("juin", "Jun"),
+    ("juillet", "Jul"),
+    ("juil", "Jul"),
+    ("août", "Aug"),
+    ("septembre", "Sep"),
+    ("sept", "Sep"),
+    ("octobre", "Oct"),
+    ("novembre", "Nov"),
+    ("décembre", "Dec"),
+    ("déc", "Dec"),
+    // Spanish
+    ("enero", "Jan"),
+    ("ene", "Jan"),
+    ("febrero", "Feb"),
+    ("marzo", "Mar"),
+    ("abril", "Apr"),
+    ("abr", "Apr"),
+    ("mayo", "May"),
+    ("junio", "Jun"),
+    ("julio", "Jul"),
+    ("agosto", "Aug"),
+    ("ago", "Aug"),
+    ("septiembre", "Sep"),
+    ("octubre", "Oct"),
+    ("noviembre", "Nov"),
+    ("diciembre", "Dec"),
+    ("dic", "Dec"),
+    // Italian
+    ("gennaio", "Jan"),
+    ("gen", "Jan"),
+    ("febbraio", "Feb"),
+    ("aprile", "Apr"),
+    ("maggio", "May"),
+    ("mag", "May"),
+    ("giugno", "Jun"),
+    ("luglio", "Jul"),
+    ("settembre", "Sep"),
+    ("ottobre", "Oct"),
+    ("ott", "Oct"),
+    ("dicembre", "Dec"),
+    // Dutch
+    ("maart", "Mar"),
+    ("mrt", "Mar"),
+    ("mei", "May"),
+];
+
+// weekday names, which are dropped before parsing (lower case, without trailing dots)
+const WEEKDAYS: &[&str] = &[
+    "mon",
+    "tue",
+    "tues",
+    "wed",
+    "thu",
+    "thur",
+    "thurs",
+    "fri",
+    "sat",
+    "sun",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+    "mo",
+    "di",
+    "mi",
+    "do",
+    "fr",
+    "sa",
+    "so",
+    "montag",
+    "dienstag",
+    "mittwoch",
+    "donnerstag",
+    "freitag",
+    "samstag",
+    "sonntag",
+    "lundi",
+    "mardi",
+    "mercredi",
+    "jeudi",
+    "vendredi",
+    "samedi",
+    "dimanche",
+    "lunes",
+    "martes",
+    "miércoles",
+    "jueves",
+    "viernes",
+    "sábado",
+    "domingo",
+    "lunedì",
+    "martedì",
+    "mercoledì",
+    "giovedì",
+    "venerdì",
+    "sabato",
+    "maandag",
+    "dinsdag",
+    "woensdag",
+    "donderdag",
+    "vrijdag",
+];
+
+pub fn parse_date(date: &str) -> Result<DateTime<FixedOffset>> {
+    let date = date.trim();
+
+    if let Ok(d) = DateTime::parse_from_rfc2822(date) {
+        return Ok(d);
+    }
+
+    if let Ok(d) = DateTime::parse_from_rfc3339(date) {
+        return Ok(d);
+    }
+
+    let normalized = normalize(date);
+
+    for format in FORMATS_WITH_OFFSET {
+        if let Ok(d) = DateTime::parse_from_str(&normalized, format) {
+            return Ok(d);
+        }
+    }
+
+    for format in FORMATS_WITHOUT_OFFSET {
+        if let Ok(d) = NaiveDateTime::parse_from_str(&normalized, format) {
+            return Ok(utc(d));
+        }
+    }
+
+    for format in DATE_FORMATS {
+        if let Some(d) = NaiveDate::parse_from_str(&normalized, format)
+            .ok()
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+        {
+            return Ok(utc(d));
+        }
+    }
+
+    Err(anyhow!("error parsing date \"{}\"", date))
+}
+
+/// Dates without offset are taken to be in UTC.
+fn utc(date: NaiveDateTime) -> DateTime<FixedOffset> {
+    // an offset of zero is always in range
+    DateTime::from_naive_utc_and_offset(date, FixedOffset::east_opt(0).unwrap())
+}
+
+fn normalize(date: &str) -> String {
+    let replaced = date.replace(',', " ");
+    let mut tokens = replaced
+        .split_whitespace()
+        .map(|t| t.trim_end_matches('.'))
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<&str>>();
+
+    if let Some(first) = tokens.first() {
+        if WEEKDAYS.contains(&&*first.to_lowercase()) {
+            tokens.remove(0);
+        }
+    }
+
+    let mut tokens = tokens
+        .into_iter()
+        .map(|t| {
+            let lower = t.to_lowercase();
+
+            match MONTHS.iter().find(|(name, _)| *name == lower) {
+                Some((_, month)) => String::from(*month),
+                None => String::from(t),
+            }
+        })
+        .collect::<Vec<String>>();
+
+    if let Some(last) = tokens.pop() {
+        tokens.push(normalize_timezone(&last).unwrap_or(last));
+    }
+
+    tokens.join(" ")
+}
+
+/// Converts timezone abbreviations ("CEST"), prefixed offsets ("GMT+2"), and short offsets
+/// ("+2", "+01") into offsets of the form "+hhmm".
+fn normalize_timezone(token: &str) -> Option<String> {
+    let upper = token.to_uppercase();
+
+    if let Some((_, minutes)) = TIMEZONES.iter().find(|(name, _)| *name == upper) {
+        return Some(format_offset(*minutes));
+    }
+
+    let offset = ["GMT", "UTC", "UT"]
+        .iter()
+        .find_map(|prefix| upper.strip_prefix(prefix))
+        .unwrap_or(&upper);
+    let (sign, digits) = match offset.chars().next() {
+        Some('+') => (1, &offset[1..]),
+        Some('-') => (-1, &offset[1..]),
+        _ => return None,
+    };
+    let digits = digits.replace(':', "");
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    match digits.len() {
+        1 | 2 => Some(format_offset(sign * digits.parse::<i32>().ok()? * 60)),
+        4 => Some(format!("{}{}", if sign < 0 { '-' } else { '+' }, digits)),
+        _ => None,
+    }
+}
+
+fn format_offset(minutes: i32) -> String {
+    format!(
+        "{}{:02}{:02}",
+        if minutes < 0 { '-' } else { '+' },
+        minutes.abs() / 60,
+        minutes.abs() % 60
+    )
+}
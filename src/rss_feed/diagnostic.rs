@@ -0,0 +1,97 @@
+use anyhow::anyhow;
+use roxmltree::Node;
+use serde::Serialize;
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// the feed was used, but a part of it was ignored or repaired
+    Warning,
+    /// a part of the feed was dropped
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(anyhow!("unknown severity \"{}\"", s)),
+        }
+    }
+}
+
+/// A problem found while parsing a feed; the path looks like "/rss/channel[1]/item[3]".
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub path: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn warning(node: Node, message: impl Into<String>) -> Self {
+        Self {
+            path: element_path(node),
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+
+    pub fn error(node: Node, message: impl Into<String>) -> Self {
+        Self {
+            path: element_path(node),
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+}
+
+impl TryFrom<&tokio_postgres::Row> for Diagnostic {
+    type Error = anyhow::Error;
+
+    fn try_from(row: &tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            path: row.try_get("path")?,
+            severity: row.try_get::<_, &str>("severity")?.parse()?,
+            message: row.try_get("message")?,
+        })
+    }
+}
+
+fn element_path(node: Node) -> String {
+    let mut names = std::iter::successors(Some(node), |n| n.parent())
+        .filter(|n| n.is_element())
+        .map(|n| {
+            let name = n.tag_name().name();
+
+            match n.parent().filter(|p| p.is_element()) {
+                Some(parent) => {
+                    let position = parent
+                        .children()
+                        .filter(|c| c.tag_name().name() == name)
+                        .take_while(|c| c != &n)
+                        .count();
+
+                    format!("{}[{}]", name, position + 1)
+                }
+                None => String::from(name),
+            }
+        })
+        .collect::<Vec<String>>();
+
+    names.reverse();
+    format!("/{}", names.join("/"))
+}
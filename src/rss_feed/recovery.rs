@@ -0,0 +1,190 @@
+use serde::Serialize;
+
+// HTML entities for the code points 160 to 255
+const LATIN_1_ENTITIES: [&str; 96] = [
+    "nbsp", "iexcl", "cent", "pound", "curren", "yen", "brvbar", "sect", "uml", "copy", "ordf",
+    "laquo", "not", "shy", "reg", "macr", "deg", "plusmn", "sup2", "sup3", "acute", "micro",
+    "para", "middot", "cedil", "sup1", "ordm", "raquo", "frac14", "frac12", "frac34", "iquest",
+    "Agrave", "Aacute", "Acirc", "Atilde", "Auml", "Aring", "AElig", "Ccedil", "Egrave", "Eacute",
+    "Ecirc", "Euml", "Igrave", "Iacute", "Icirc", "Iuml", "ETH", "Ntilde", "Ograve", "Oacute",
+    "Ocirc", "Otilde", "Ouml", "times", "Oslash", "Ugrave", "Uacute", "Ucirc", "Uuml", "Yacute",
+    "THORN", "szlig", "agrave", "aacute", "acirc", "atilde", "auml", "aring", "aelig", "ccedil",
+    "egrave", "eacute", "ecirc", "euml", "igrave", "iacute", "icirc", "iuml", "eth", "ntilde",
+    "ograve", "oacute", "ocirc", "otilde", "ouml", "divide", "oslash", "ugrave", "uacute", "ucirc",
+    "uuml", "yacute", "thorn", "yuml",
+];
+
+const OTHER_ENTITIES: [(&str, u32); 32] = [
+    ("OElig", 338),
+    ("oelig", 339),
+    ("Scaron", 352),
+    ("scaron", 353),
+    ("Yuml", 376),
+    ("fnof", 402),
+    ("circ", 710),
+    ("tilde", 732),
+    ("ensp", 8194),
+    ("emsp", 8195),
+    ("thinsp", 8201),
+    ("zwnj", 8204),
+    ("zwj", 8205),
+    ("lrm", 8206),
+    ("rlm", 8207),
+    ("ndash", 8211),
+    ("mdash", 8212),
+    ("lsquo", 8216),
+    ("rsquo", 8217),
+    ("sbquo", 8218),
+    ("ldquo", 8220),
+    ("rdquo", 8221),
+    ("bdquo", 8222),
+    ("dagger", 8224),
+    ("Dagger", 8225),
+    ("bull", 8226),
+    ("hellip", 8230),
+    ("permil", 8240),
+    ("lsaquo", 8249),
+    ("rsaquo", 8250),
+    ("euro", 8364),
+    ("trade", 8482),
+];
+
+const XML_ENTITIES: [&str; 5] = ["amp", "lt", "gt", "quot", "apos"];
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct RecoveryReport {
+    pub byte_order_mark_removed: bool,
+    pub leading_whitespace_removed: bool,
+    pub control_characters_removed: usize,
+    pub entities_resolved: usize,
+    pub ampersands_escaped: usize,
+}
+
+impl RecoveryReport {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Repairs common defects of feeds, which are not well-formed XML.
+///
+/// HTML entities are replaced by character references, stray ampersands are escaped, and
+/// characters not allowed in XML are removed. CDATA sections and comments are left intact
+/// apart from the removal of illegal characters.
+pub fn recover(input: &str) -> (String, RecoveryReport) {
+    let mut report = RecoveryReport::default();
+    let mut rest = input;
+
+    if let Some(stripped) = rest.strip_prefix('\u{FEFF}') {
+        report.byte_order_mark_removed = true;
+        rest = stripped;
+    }
+
+    if rest.starts_with(char::is_whitespace) {
+        report.leading_whitespace_removed = true;
+        rest = rest.trim_start();
+    }
+
+    let mut output = String::with_capacity(rest.len());
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(verbatim) = verbatim_section(rest) {
+            push_legal(&mut output, verbatim, &mut report);
+            rest = &rest[verbatim.len()..];
+        } else if c == '&' {
+            let (reference, length) = repair_reference(&rest[1..], &mut report);
+
+            output.push_str(&reference);
+            rest = &rest[1 + length..];
+        } else {
+            push_legal(&mut output, &rest[..c.len_utf8()], &mut report);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    (output, report)
+}
+
+fn verbatim_section(input: &str) -> Option<&str> {
+    for (start, end) in [("<![CDATA[", "]]>"), ("<!--", "-->")] {
+        if input.starts_with(start) {
+            return Some(match input[start.len()..].find(end) {
+                Some(pos) => &input[..start.len() + pos + end.len()],
+                None => input,
+            });
+        }
+    }
+
+    None
+}
+
+fn push_legal(output: &mut String, input: &str, report: &mut RecoveryReport) {
+    for c in input.chars() {
+        if is_xml_char(c) {
+            output.push(c);
+        } else {
+            report.control_characters_removed += 1;
+        }
+    }
+}
+
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r')
+        || matches!(c, '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}')
+        || c >= '\u{10000}'
+}
+
+/// Repairs the reference following an ampersand; returns the replacement (including the
+/// ampersand) and the number of bytes consumed after the ampersand.
+fn repair_reference(input: &str, report: &mut RecoveryReport) -> (String, usize) {
+    let name_length = input
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#'))
+        .unwrap_or(input.len());
+
+    if name_length == 0 || name_length > 32 || !input[name_length..].starts_with(';') {
+        report.ampersands_escaped += 1;
+        return (String::from("&amp;"), 0);
+    }
+
+    let name = &input[..name_length];
+    let code = match name.strip_prefix('#') {
+        Some(number) => match number.strip_prefix('x').or(number.strip_prefix('X')) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => number.parse::<u32>().ok(),
+        },
+        None if XML_ENTITIES.contains(&name) => return (format!("&{};", name), name_length + 1),
+        None => html_entity(name),
+    };
+
+    match code.map(char::from_u32) {
+        Some(Some(c)) if is_xml_char(c) => {
+            if !name.starts_with('#') {
+                report.entities_resolved += 1;
+            }
+
+            (format!("&#{};", c as u32), name_length + 1)
+        }
+        Some(_) => {
+            // a reference to a character, which is not allowed in XML
+            report.control_characters_removed += 1;
+            (String::new(), name_length + 1)
+        }
+        None => {
+            report.ampersands_escaped += 1;
+            (String::from("&amp;"), 0)
+        }
+    }
+}
+
+fn html_entity(name: &str) -> Option<u32> {
+    LATIN_1_ENTITIES
+        .iter()
+        .position(|&e| e == name)
+        .map(|pos| pos as u32 + 160)
+        .or_else(|| {
+            OTHER_ENTITIES
+                .iter()
+                .find(|(e, _)| *e == name)
+                .map(|(_, code)| *code)
+        })
+}
@@ -1,8 +1,8 @@
 use chrono::{DateTime, TimeZone, Utc};
 
-use crate::rss_feed::{RssEnclosure, RssItem};
+use crate::rss_feed::{RssAlternateEnclosure, RssChannelMetadata, RssEnclosure, RssItem};
 
-use super::{RssChannel, RssFeed};
+use super::{Diagnostic, ParseOptions, RecoveryReport, RssChannel, RssFeed, Severity};
 use std::{convert::TryFrom, fs};
 
 #[test]
@@ -16,23 +16,37 @@ fn parse_bots() {
             title: String::from("O'Reilly Bots Podcast - O'Reilly Media Podcast"),
             description: String::from("The O'Reilly Bots Podcast covers advances in conversational user interfaces, artificial intelligence, and messaging that are revolutionizing the way we interact with software."),
             image: Some(String::from("http://cdn.oreilly.com/radar/bot-podcast/avatar_Bots_1400x1400.png")),
+            metadata: RssChannelMetadata {
+                author: Some(String::from("O'Reilly Media")),
+                language: Some(String::from("en-US")),
+                categories: vec![String::from("Society &amp; Culture"), String::from("Business"), String::from("Technology/Tech News")],
+                link: Some(String::from("https://www.oreilly.com")),
+                copyright: Some(String::from("O'Reilly Media Inc.")),
+                owner_name: Some(String::from("O'Reilly Media")),
+                owner_email: Some(String::from("onlinecap@oreilly.com")),
+                show_type: None,
+            },
             items: vec![RssItem {
                 title: String::from("Jason Laska and Michael Akilian on using AI to schedule meetings"),
-                date: Utc.ymd(2017, 05, 25).and_hms(10, 30, 00).into(),
-                enclosure: RssEnclosure {
+                date: Some(Utc.ymd(2017, 05, 25).and_hms(10, 30, 00).into()),
+                enclosure: Some(RssEnclosure {
                     url: String::from("http://dts.podtrac.com/redirect.mp3/cdn.oreillystatic.com/radar/bot-podcast/Jason_Laska_and_Michael_Akilian_on_scheduling_bots.mp3"),
                     mime_type: String::from("audio/mpeg"),
                     length: 44983910,
-                }
+                }),
+                alternate_enclosures: vec![],
+                image: None
             },
             RssItem {
                 title: String::from("Chris Messina on Facebook as a utility"),
-                date: Utc.ymd(2017, 05, 11).and_hms(10, 45, 00).into(),
-                enclosure: RssEnclosure {
+                date: Some(Utc.ymd(2017, 05, 11).and_hms(10, 45, 00).into()),
+                enclosure: Some(RssEnclosure {
                     url: String::from("http://dts.podtrac.com/redirect.mp3/cdn.oreillystatic.com/radar/bot-podcast/Chris_Messina_on_Facebook_as_a_utility.mp3"),
                     mime_type: String::from("audio/mpeg"),
                     length: 68157440,
-                }
+                }),
+                alternate_enclosures: vec![],
+                image: None
             }],
         }
     );
@@ -49,33 +63,49 @@ fn parse_c_radar() {
             title: String::from("C-RadaR"),
             description: String::from("Monatliche Radiosendung des Chaos Computer Clubs auf Radio Darmstadt. Jeden 2ten Donnerstag im Monat, 21-23 Uhr. 103,4 MHz / 99,85 MHz im Kabel / Stream. Tune In!"),
             image: Some(String::from("https://www.c-radar.de/wp-content/uploads/2017/07/Cradar_Logo1.jpg")),
+            metadata: RssChannelMetadata {
+                author: Some(String::from("C-RadaR")),
+                language: Some(String::from("de-DE")),
+                categories: vec![String::from("Technology/Tech News")],
+                link: Some(String::from("https://www.c-radar.de")),
+                copyright: None,
+                owner_name: Some(String::from("C-RadaR")),
+                owner_email: Some(String::from("info@c-radar.de")),
+                show_type: Some(String::from("serial")),
+            },
             items: vec![
                 RssItem {
                     title: String::from("c-radar September 2017 – Martin Tschirsich zu PC-Wahl, Piratenspitzenkandidat Sebulino und ein Überraschungsgast"),
-                    date: DateTime::parse_from_rfc3339("2017-09-15T11:00:36.000Z").unwrap(),
-                    enclosure: RssEnclosure {
+                    date: Some(DateTime::parse_from_rfc3339("2017-09-15T11:00:36.000Z").unwrap()),
+                    enclosure: Some(RssEnclosure {
                         url: String::from("http://ftp.ccc.de/broadcast/c-radar/c-radar/2017/c-radar-09-2017.mp3"),
                         mime_type: String::from("audio/mpeg"),
                         length: 285600026,
-                    }
+                    }),
+                    alternate_enclosures: vec![],
+                    image: None
                 },
                 RssItem {
                     title: "c-radar Juli 2017 – zwei Berichte vom G20 in HH; NODE Forum, CCC Regiotreff,  Grundrechte Demo in Berlin".into(),
-                    date: DateTime::parse_from_rfc3339("2017-07-27T20:39:43.000Z").unwrap(),
-                    enclosure: RssEnclosure {
+                    date: Some(DateTime::parse_from_rfc3339("2017-07-27T20:39:43.000Z").unwrap()),
+                    enclosure: Some(RssEnclosure {
                         url: "http://ftp.ccc.de/broadcast/c-radar/c-radar/2017/c-radar-07-2017.mp3".into(),
                         mime_type: "audio/mpeg".into(),
                         length: 287815167,
-                    }
+                    }),
+                    alternate_enclosures: vec![],
+                    image: None
                 },
                 RssItem {
                     title: "c-radar Juni 2017 – BalCCon, FF Hessentag, Night of Science, SHA2017, Debian Release Party".into(),
-                    date: DateTime::parse_from_rfc3339("2017-06-12T08:33:35.000Z").unwrap(),
-                    enclosure: RssEnclosure {
+                    date: Some(DateTime::parse_from_rfc3339("2017-06-12T08:33:35.000Z").unwrap()),
+                    enclosure: Some(RssEnclosure {
                         url: "http://ftp.ccc.de/broadcast/c-radar/c-radar/2017/c-radar-06-2017.mp3".into(),
                         mime_type: "audio/mpeg".into(),
                         length: 288079553,
-                    }
+                    }),
+                    alternate_enclosures: vec![],
+                    image: None
                 }
             ],
         }
@@ -93,6 +123,16 @@ fn parse_empty() {
             title: String::from("C-RadaR"),
             description: String::from("Monatliche Radiosendung des Chaos Computer Clubs auf Radio Darmstadt. Jeden 2ten Donnerstag im Monat, 21-23 Uhr. 103,4 MHz / 99,85 MHz im Kabel / Stream. Tune In!"),
             image: None,
+            metadata: RssChannelMetadata {
+                author: Some(String::from("C-RadaR")),
+                language: Some(String::from("de-DE")),
+                categories: vec![String::from("Technology/Tech News")],
+                link: Some(String::from("https://www.c-radar.de")),
+                copyright: None,
+                owner_name: Some(String::from("C-RadaR")),
+                owner_email: Some(String::from("info@c-radar.de")),
+                show_type: Some(String::from("serial")),
+            },
             items: vec![],
         }
     );
@@ -112,24 +152,38 @@ fn parse_lin_digres() {
                 "search for me"
             ),
             image: None,
+            metadata: RssChannelMetadata {
+                author: None,
+                language: Some(String::from("en-US")),
+                categories: vec![],
+                link: Some(String::from("http://lineardigressions.com/")),
+                copyright: None,
+                owner_name: None,
+                owner_email: None,
+                show_type: None,
+            },
             items: vec![
                 RssItem {
                     title: "Autoencoders".into(),
-                    date: DateTime::parse_from_rfc3339("2018-03-12T01:46:48Z").unwrap(),
-                    enclosure: RssEnclosure {
+                    date: Some(DateTime::parse_from_rfc3339("2018-03-12T01:46:48Z").unwrap()),
+                    enclosure: Some(RssEnclosure {
                         url: "http://static1.squarespace.com/static/56c89b14c2ea51c475ee830b/t/5aa5dbc6c8302542e9d31410/1520819158384/autoencoders+produced.mp3".into(),
                         mime_type: "audio/mpeg".into(),
                         length: 6090534,
-                    }
+                    }),
+                    alternate_enclosures: vec![],
+                    image: None
                 },
                 RssItem {
                     title: "When is open data too open?".into(),
-                    date: DateTime::parse_from_rfc3339("2018-03-05T03:34:55Z").unwrap(),
-                    enclosure: RssEnclosure {
+                    date: Some(DateTime::parse_from_rfc3339("2018-03-05T03:34:55Z").unwrap()),
+                    enclosure: Some(RssEnclosure {
                         url: "http://static1.squarespace.com/static/56c89b14c2ea51c475ee830b/t/5a9cba3dc830255b24811f2b/1520220754818/data+privacy+produced.mp3".into(),
                         mime_type: "audio/mpeg".into(),
                         length: 12644552,
-                    }
+                    }),
+                    alternate_enclosures: vec![],
+                    image: None
                 }
             ],
         }
@@ -148,23 +202,37 @@ fn parse_minkorrekt() {
             title: String::from("Methodisch inkorrekt!"),
             description: String::from("der methodisch inkorrekte Wissenschaftspodcast"),
             image: Some(String::from("https://images.podigee-cdn.net/0x,srhipgyug44BeyTko7VrGtswPXmBVkFavcRQPA4gts-E=/https://cdn.podigee.com/uploads/u2487/51751e79-4c75-43cc-b213-8e0cd040f439.jpg")),
+            metadata: RssChannelMetadata {
+                author: Some(String::from("Methodisch inkorrekt!")),
+                language: Some(String::from("de")),
+                categories: vec![String::from("Science")],
+                link: Some(String::from("http://www.minkorrekt.de")),
+                copyright: Some(String::from("CC 3.0")),
+                owner_name: Some(String::from("Methodisch inkorrekt!")),
+                owner_email: Some(String::from("info@methodisch-inkorrekt.de")),
+                show_type: Some(String::from("episodic")),
+            },
             items: vec![RssItem {
                 title: String::from("Mi206 - \"Zwischen den Jahren\""),
-                date: Utc.ymd(2021, 12, 29).and_hms(09, 55, 58).into(),
-                enclosure: RssEnclosure {
+                date: Some(Utc.ymd(2021, 12, 29).and_hms(09, 55, 58).into()),
+                enclosure: Some(RssEnclosure {
                     url: String::from("https://cdn.podigee.com/media/podcast_3451_methodisch_inkorrekt_episode_627156_mi206_zwischen_den_jahren.mp3?v=1640772750&source=feed"),
                     mime_type: String::from("audio/mpeg"),
                     length: 253548623,
-                }
+                }),
+                alternate_enclosures: vec![],
+                image: Some(String::from("https://images.podigee-cdn.net/0x,sogAhHk-phu0zif0VG5oVOGeVjOq_MrAqjwntxoH5E20=/https://cdn.podigee.com/uploads/u2487/66460a2a-a47e-4025-b593-9d18920e29a2.jpg"))
             },
             RssItem {
                 title: String::from("Mi205 - \"Mettbett\""),
-                date: Utc.ymd(2021, 12, 14).and_hms(18, 55, 06).into(),
-                enclosure: RssEnclosure {
+                date: Some(Utc.ymd(2021, 12, 14).and_hms(18, 55, 06).into()),
+                enclosure: Some(RssEnclosure {
                     url: String::from("https://adn.podigee.com/adswizz/media/podcast_3451_methodisch_inkorrekt_episode_615122_mi205_mettbett.mp3?awCollectionId=svo_cea0cf&awEpisodeId=615122&source=feed&v=1639560073"),
                     mime_type: String::from("audio/mpeg"),
                     length: 302222176,
-                }
+                }),
+                alternate_enclosures: vec![],
+                image: Some(String::from("https://images.podigee-cdn.net/0x,saMxHeFygPH6oYA8XXL32dE4I7pP26vRIDXWEPpOSQdk=/https://cdn.podigee.com/uploads/u2487/4d853e3b-232b-4b82-bb8d-7b776f937e80.jpg"))
             }],
         }
     );
@@ -194,11 +262,280 @@ fn itunes_summary() {
             title: String::from("title"),
             description: String::from("itunes summary"),
             image: None,
+            metadata: RssChannelMetadata::default(),
             items: vec![]
         }
     );
 }
 
+#[test]
+fn new_feed_url() {
+    let feed =
+        RssFeed::try_from(&*fs::read_to_string("testFiles/new_feed_url.xml").unwrap()).unwrap();
+
+    assert_eq!(
+        feed.new_feed_url,
+        Some(String::from("https://example.com/new/feed.xml"))
+    );
+
+    let feed = RssFeed::try_from(&*fs::read_to_string("testFiles/bots.xml").unwrap()).unwrap();
+
+    assert_eq!(feed.new_feed_url, None);
+}
+
+#[test]
+fn unknown_show_type() {
+    let feed = RssFeed::try_from(&*fs::read_to_string("testFiles/unknown_show_type.xml").unwrap())
+        .unwrap();
+
+    assert_eq!(feed.channels[0].metadata.show_type, None);
+}
+
+#[test]
+fn drop_incomplete_items() {
+    let feed =
+        RssFeed::try_from(&*fs::read_to_string("testFiles/incomplete_items.xml").unwrap()).unwrap();
+
+    assert_eq!(feed.channels[0].items.len(), 1);
+    assert_eq!(feed.channels[0].items[0].title, "Complete");
+}
+
+#[test]
+fn keep_incomplete_items() {
+    let (feed, _) = RssFeed::parse_lenient(
+        &*fs::read_to_string("testFiles/incomplete_items.xml").unwrap(),
+        &ParseOptions {
+            keep_incomplete_items: true,
+        },
+    )
+    .unwrap();
+    let items = &feed.channels[0].items;
+
+    assert_eq!(items.len(), 4);
+    assert!(items[0].date.is_some() && items[0].enclosure.is_some());
+    assert_eq!(items[1].title, "Without date");
+    assert_eq!(items[1].date, None);
+    assert!(items[1].enclosure.is_some());
+    assert_eq!(items[2].title, "Without enclosure");
+    assert!(items[2].date.is_some());
+    assert_eq!(items[2].enclosure, None);
+    assert_eq!(items[3].title, "Invalid date");
+    assert_eq!(items[3].date, None);
+}
+
+#[test]
+fn item_images() {
+    let feed =
+        RssFeed::try_from(&*fs::read_to_string("testFiles/item_images.xml").unwrap()).unwrap();
+
+    assert_eq!(
+        feed.channels[0].image.as_deref(),
+        Some("https://example.com/channel.jpg")
+    );
+    assert_eq!(
+        feed.channels[0]
+            .items
+            .iter()
+            .map(|i| i.image.as_deref())
+            .collect::<Vec<Option<&str>>>(),
+        vec![
+            Some("https://example.com/1.jpg"),
+            Some("https://example.com/2_thumb.jpg"),
+            Some("https://example.com/3.jpg"),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn diagnostics_of_dropped_items() {
+    let feed =
+        RssFeed::try_from(&*fs::read_to_string("testFiles/incomplete_items.xml").unwrap()).unwrap();
+
+    assert_eq!(
+        feed.diagnostics
+            .iter()
+            .map(|d| (d.path.as_str(), d.severity))
+            .collect::<Vec<(&str, Severity)>>(),
+        vec![
+            ("/rss/channel[1]/item[2]", Severity::Error),
+            ("/rss/channel[1]/item[3]", Severity::Error),
+            ("/rss/channel[1]/item[4]", Severity::Error),
+            ("/rss/channel[1]/item[5]", Severity::Error),
+        ]
+    );
+    assert_eq!(
+        feed.diagnostics[0].message,
+        "item dropped: could not find date for item"
+    );
+}
+
+#[test]
+fn diagnostics_of_kept_items() {
+    let (feed, _) = RssFeed::parse_lenient(
+        &*fs::read_to_string("testFiles/incomplete_items.xml").unwrap(),
+        &ParseOptions {
+            keep_incomplete_items: true,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        feed.diagnostics,
+        vec![
+            Diagnostic {
+                path: String::from("/rss/channel[1]/item[2]"),
+                severity: Severity::Warning,
+                message: String::from("date estimated: missing pubDate"),
+            },
+            Diagnostic {
+                path: String::from("/rss/channel[1]/item[3]"),
+                severity: Severity::Warning,
+                message: String::from("item kept without enclosure"),
+            },
+            Diagnostic {
+                path: String::from("/rss/channel[1]/item[4]"),
+                severity: Severity::Warning,
+                message: String::from("date estimated: error parsing date \"sometime last week\""),
+            },
+            Diagnostic {
+                path: String::from("/rss/channel[1]/item[5]"),
+                severity: Severity::Error,
+                message: String::from("item dropped: could not find title for item"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn diagnostics_of_repaired_feed() {
+    let xml = fs::read_to_string("testFiles/broken_ampersand.xml").unwrap();
+    let (feed, _) = RssFeed::parse_lenient(&*xml, &ParseOptions::default()).unwrap();
+
+    assert_eq!(feed.diagnostics[0].path, "/rss");
+    assert_eq!(feed.diagnostics[0].severity, Severity::Warning);
+    assert!(feed.diagnostics[0]
+        .message
+        .starts_with("feed is not well-formed"));
+}
+
+#[test]
+fn alternate_enclosures() {
+    let feed =
+        RssFeed::try_from(&*fs::read_to_string("testFiles/alternate_enclosures.xml").unwrap())
+            .unwrap();
+    let item = &feed.channels[0].items[0];
+
+    assert_eq!(
+        item.enclosure.as_ref().unwrap().url,
+        "https://example.com/episode1.mp3"
+    );
+    assert_eq!(
+        item.alternate_enclosures,
+        vec![
+            RssAlternateEnclosure {
+                url: String::from("https://example.com/episode1.m4a"),
+                mime_type: String::from("audio/mp4"),
+                length: 32000000,
+                bitrate: None,
+                title: None,
+            },
+            RssAlternateEnclosure {
+                url: String::from("https://example.com/episode1_low.opus"),
+                mime_type: String::from("audio/opus"),
+                length: 16000000,
+                bitrate: Some(64000),
+                title: Some(String::from("Low bandwidth")),
+            },
+            RssAlternateEnclosure {
+                url: String::from("https://example.com/episode1.flac"),
+                mime_type: String::from("audio/flac"),
+                length: 512000000,
+                bitrate: Some(1411201),
+                title: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn lenient_well_formed() {
+    let (feed, report) = RssFeed::parse_lenient(
+        &*fs::read_to_string("testFiles/bots.xml").unwrap(),
+        &ParseOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(feed.channels[0].items.len(), 2);
+    assert!(report.is_empty());
+    assert!(feed.diagnostics.is_empty());
+}
+
+#[test]
+fn lenient_entities() {
+    let xml = fs::read_to_string("testFiles/broken_entities.xml").unwrap();
+
+    assert!(RssFeed::try_from(&*xml).is_err());
+
+    let (feed, report) = RssFeed::parse_lenient(&*xml, &ParseOptions::default()).unwrap();
+
+    assert_eq!(feed.channels[0].title, "Caf\u{e9}\u{a0}Talk");
+    assert!(feed.channels[0]
+        .description
+        .starts_with("Stories \u{2026} and more \u{2014} weekly &foo;"));
+    assert!(feed.channels[0].description.ends_with("<b>&bold;</b>"));
+    assert_eq!(
+        feed.channels[0].items[0].title,
+        "Episode\u{a0}1 \u{2013} \u{a9} & \u{2019}"
+    );
+    assert_eq!(
+        report,
+        RecoveryReport {
+            entities_resolved: 7,
+            ampersands_escaped: 1,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn lenient_ampersands() {
+    let xml = fs::read_to_string("testFiles/broken_ampersand.xml").unwrap();
+
+    assert!(RssFeed::try_from(&*xml).is_err());
+
+    let (feed, report) = RssFeed::parse_lenient(&*xml, &ParseOptions::default()).unwrap();
+
+    assert_eq!(feed.channels[0].title, "Q&A Podcast");
+    assert_eq!(feed.channels[0].description, "Tips & tricks & more");
+    assert_eq!(feed.channels[0].items[0].title, "Episode 1: Rock & Roll");
+    assert_eq!(
+        feed.channels[0].items[0].enclosure.as_ref().unwrap().url,
+        "https://example.com/episode1.mp3?a=1&b=2"
+    );
+    assert_eq!(report.ampersands_escaped, 5);
+}
+
+#[test]
+fn lenient_byte_order_mark_and_control_characters() {
+    let xml = fs::read_to_string("testFiles/broken_bom_control.xml").unwrap();
+
+    assert!(RssFeed::try_from(&*xml).is_err());
+
+    let (feed, report) = RssFeed::parse_lenient(&*xml, &ParseOptions::default()).unwrap();
+
+    assert_eq!(feed.channels[0].title, "ControlPodcast");
+    assert_eq!(feed.channels[0].items[0].title, "Episode 1");
+    assert_eq!(
+        report,
+        RecoveryReport {
+            byte_order_mark_removed: true,
+            control_characters_removed: 3,
+            ..Default::default()
+        }
+    );
+}
+
 #[test]
 fn parse_date_1() {
     assert_eq!(
@@ -214,3 +551,69 @@ fn parse_date_2() {
         DateTime::parse_from_rfc3339("2016-11-25T08:30:00Z").unwrap()
     );
 }
+
+fn assert_date(date: &str, expected: &str) {
+    assert_eq!(
+        RssFeed::parse_date(date).unwrap(),
+        DateTime::parse_from_rfc3339(expected).unwrap(),
+        "{}",
+        date
+    );
+}
+
+#[test]
+fn parse_date_timezone_abbreviations() {
+    assert_date("Tue, 13 Mar 2018 19:08:36 EST", "2018-03-14T00:08:36Z");
+    assert_date("Tue, 13 Mar 2018 19:08:36 CEST", "2018-03-13T17:08:36Z");
+    assert_date("Tue, 13 Mar 2018 19:08:36 MEZ", "2018-03-13T18:08:36Z");
+    assert_date("Tue, 13 Mar 2018 19:08:36 AEDT", "2018-03-13T08:08:36Z");
+    assert_date("Tue, 13 Mar 2018 19:08:36 UTC", "2018-03-13T19:08:36Z");
+    assert_date("Tue, 13 Mar 2018 19:08:36 Z", "2018-03-13T19:08:36Z");
+    assert_date("Tue, 13 Mar 2018 19:08:36 GMT+2", "2018-03-13T17:08:36Z");
+    assert_date("Tue, 13 Mar 2018 19:08:36 +01:00", "2018-03-13T18:08:36Z");
+}
+
+#[test]
+fn parse_date_missing_parts() {
+    assert_date("Tue, 13 Mar 2018 19:08 +0000", "2018-03-13T19:08:00Z");
+    assert_date("Tue, 13 Mar 2018 19:08 PST", "2018-03-14T03:08:00Z");
+    assert_date("13 Mar 2018 19:08:36", "2018-03-13T19:08:36Z");
+    assert_date("Tue, 13 Mar 2018", "2018-03-13T00:00:00Z");
+    assert_date("Tue,13 Mar 2018 19:08:36 +0000", "2018-03-13T19:08:36Z");
+}
+
+#[test]
+fn parse_date_iso_8601() {
+    assert_date("2018-03-13T19:08:36Z", "2018-03-13T19:08:36Z");
+    assert_date("2018-03-13T19:08:36.123+01:00", "2018-03-13T18:08:36.123Z");
+    assert_date("2018-03-13T19:08:36+0100", "2018-03-13T18:08:36Z");
+    assert_date("2018-03-13 19:08:36 -05:00", "2018-03-14T00:08:36Z");
+    assert_date("2018-03-13T19:08:36", "2018-03-13T19:08:36Z");
+    assert_date("2018-03-13 19:08", "2018-03-13T19:08:00Z");
+    assert_date("2018-03-13", "2018-03-13T00:00:00Z");
+}
+
+#[test]
+fn parse_date_localized() {
+    assert_date("Di, 13 Mär 2018 19:08:36 +0100", "2018-03-13T18:08:36Z");
+    assert_date(
+        "Dienstag, 13. März 2018 19:08:36 MEZ",
+        "2018-03-13T18:08:36Z",
+    );
+    assert_date("13. Oktober 2021 10:00:00 MESZ", "2021-10-13T08:00:00Z");
+    assert_date("mardi, 13 mars 2018 19:08:36 +0100", "2018-03-13T18:08:36Z");
+    assert_date("13 févr. 2018 19:08:36 +0100", "2018-02-13T18:08:36Z");
+    assert_date("martes, 13 dic 2018 19:08:36 +0100", "2018-12-13T18:08:36Z");
+    assert_date("13 maggio 2018 19:08:36 +0200", "2018-05-13T17:08:36Z");
+    assert_date(
+        "Tuesday, 13 March 2018 19:08:36 +0000",
+        "2018-03-13T19:08:36Z",
+    );
+}
+
+#[test]
+fn parse_date_invalid() {
+    assert!(RssFeed::parse_date("").is_err());
+    assert!(RssFeed::parse_date("yesterday").is_err());
+    assert!(RssFeed::parse_date("13 Foo 2018 19:08:36 +0000").is_err());
+}
@@ -0,0 +1,163 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, fmt, str::FromStr};
+use uuid::Uuid;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelMeta {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+    pub active: bool,
+    pub volume: f64,
+    pub playback_rate: f64,
+    /// time of the change on the client
+    pub update_ts: DateTime<FixedOffset>,
+}
+
+impl ChannelMeta {
+    /// Resolves a change of a client against the server's state; the last writer wins.
+    ///
+    /// Returns the metadata to store or `None` if the server's state remains unchanged.
+    pub fn resolve(&self, server: Option<&Self>) -> Option<Self> {
+        match server {
+            None => Some(self.clone()),
+            Some(server) if self.update_ts > server.update_ts => Some(Self {
+                id: server.id,
+                ..self.clone()
+            })
+            .filter(|resolved| resolved != server),
+            Some(_) => None,
+        }
+    }
+}
+
+impl TryFrom<&tokio_postgres::Row> for ChannelMeta {
+    type Error = anyhow::Error;
+
+    fn try_from(row: &tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            channel_id: row.try_get("channel_id")?,
+            active: row.try_get("active")?,
+            volume: row.try_get("volume")?,
+            playback_rate: row.try_get("playback_rate")?,
+            update_ts: row.try_get("update_ts")?,
+        })
+    }
+}
+
+/// The values of the download_status type of the schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DownloadStatus {
+    NotRequested,
+    Pending,
+    InProgress,
+    Ok,
+    Error,
+}
+
+impl fmt::Display for DownloadStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadStatus::NotRequested => write!(f, "NotRequested"),
+            DownloadStatus::Pending => write!(f, "Pending"),
+            DownloadStatus::InProgress => write!(f, "InProgress"),
+            DownloadStatus::Ok => write!(f, "Ok"),
+            DownloadStatus::Error => write!(f, "Error"),
+        }
+    }
+}
+
+impl FromStr for DownloadStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "NotRequested" => Ok(DownloadStatus::NotRequested),
+            "Pending" => Ok(DownloadStatus::Pending),
+            "InProgress" => Ok(DownloadStatus::InProgress),
+            "Ok" => Ok(DownloadStatus::Ok),
+            "Error" => Ok(DownloadStatus::Error),
+            _ => Err(anyhow!("unknown download status \"{}\"", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemMeta {
+    pub id: Uuid,
+    pub item_id: Uuid,
+    pub new: bool,
+    pub download_status: DownloadStatus,
+    pub playback_time: Option<f64>,
+    pub play_count: i32,
+    /// time of the change on the client
+    pub update_ts: DateTime<FixedOffset>,
+}
+
+impl ItemMeta {
+    /// Resolves a change of a client against the server's state.
+    ///
+    /// The last writer wins except for the playback time, of which the furthest progress is
+    /// kept. Returns the metadata to store or `None` if the server's state remains unchanged.
+    pub fn resolve(&self, server: Option<&Self>) -> Option<Self> {
+        let server = match server {
+            Some(server) => server,
+            None => return Some(self.clone()),
+        };
+        let latest = if self.update_ts > server.update_ts {
+            self
+        } else {
+            server
+        };
+        let playback_time = match (self.playback_time, server.playback_time) {
+            (Some(client), Some(server)) => Some(client.max(server)),
+            (client, server) => client.or(server),
+        };
+        let resolved = Self {
+            id: server.id,
+            playback_time,
+            ..latest.clone()
+        };
+
+        Some(resolved).filter(|resolved| resolved != server)
+    }
+}
+
+impl TryFrom<&tokio_postgres::Row> for ItemMeta {
+    type Error = anyhow::Error;
+
+    fn try_from(row: &tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            item_id: row.try_get("item_id")?,
+            new: row.try_get("new")?,
+            download_status: row.try_get::<_, &str>("download_status")?.parse()?,
+            playback_time: row.try_get("playback_time")?,
+            play_count: row.try_get("play_count")?,
+            update_ts: row.try_get("update_ts")?,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncRequest {
+    /// the token returned by the previous sync; all metadata is returned without it
+    pub token: Option<String>,
+    #[serde(default)]
+    pub channel_metas: Vec<ChannelMeta>,
+    #[serde(default)]
+    pub item_metas: Vec<ItemMeta>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncResponse {
+    pub token: String,
+    /// metadata changed since the previous sync including the resolved changes of the client
+    pub channel_metas: Vec<ChannelMeta>,
+    pub item_metas: Vec<ItemMeta>,
+}
@@ -0,0 +1,124 @@
+use super::{ChannelMeta, DownloadStatus, ItemMeta, SyncRequest};
+use chrono::{DateTime, FixedOffset};
+use uuid::Uuid;
+
+fn ts(time: &str) -> DateTime<FixedOffset> {
+    DateTime::parse_from_rfc3339(&format!("2022-03-01T{}+00:00", time)).unwrap()
+}
+
+fn channel_meta(active: bool, update_ts: &str) -> ChannelMeta {
+    ChannelMeta {
+        id: Uuid::new_v4(),
+        channel_id: Uuid::nil(),
+        active,
+        volume: 1.0,
+        playback_rate: 1.0,
+        update_ts: ts(update_ts),
+    }
+}
+
+fn item_meta(play_count: i32, playback_time: Option<f64>, update_ts: &str) -> ItemMeta {
+    ItemMeta {
+        id: Uuid::new_v4(),
+        item_id: Uuid::nil(),
+        new: false,
+        download_status: DownloadStatus::Ok,
+        playback_time,
+        play_count,
+        update_ts: ts(update_ts),
+    }
+}
+
+#[test]
+fn channel_meta_new() {
+    let client = channel_meta(true, "10:00:00");
+
+    assert_eq!(client.resolve(None), Some(client.clone()));
+}
+
+#[test]
+fn channel_meta_last_writer_wins() {
+    let server = channel_meta(true, "10:00:00");
+    let client = channel_meta(false, "11:00:00");
+
+    assert_eq!(
+        client.resolve(Some(&server)),
+        Some(ChannelMeta {
+            id: server.id,
+            ..client.clone()
+        })
+    );
+    assert_eq!(server.resolve(Some(&client)), None);
+}
+
+#[test]
+fn channel_meta_unchanged() {
+    let server = channel_meta(true, "10:00:00");
+    let client = ChannelMeta {
+        update_ts: ts("11:00:00"),
+        ..server.clone()
+    };
+
+    assert_eq!(
+        client.resolve(Some(&server)).map(|m| m.update_ts),
+        Some(ts("11:00:00"))
+    );
+    assert_eq!(server.clone().resolve(Some(&server)), None);
+}
+
+#[test]
+fn item_meta_keeps_furthest_progress() {
+    let server = item_meta(1, Some(600.0), "10:00:00");
+    let client = item_meta(2, Some(300.0), "11:00:00");
+    let resolved = client.resolve(Some(&server)).unwrap();
+
+    assert_eq!(resolved.id, server.id);
+    assert_eq!(resolved.play_count, 2);
+    assert_eq!(resolved.playback_time, Some(600.0));
+    assert_eq!(resolved.update_ts, ts("11:00:00"));
+}
+
+#[test]
+fn item_meta_progress_of_older_change() {
+    let server = item_meta(1, Some(300.0), "11:00:00");
+    let client = item_meta(2, Some(600.0), "10:00:00");
+    let resolved = client.resolve(Some(&server)).unwrap();
+
+    assert_eq!(resolved.play_count, 1);
+    assert_eq!(resolved.playback_time, Some(600.0));
+    assert_eq!(resolved.update_ts, ts("11:00:00"));
+
+    let client = item_meta(2, None, "10:00:00");
+
+    assert_eq!(client.resolve(Some(&server)), None);
+}
+
+#[test]
+fn download_status() {
+    for status in [
+        DownloadStatus::NotRequested,
+        DownloadStatus::Pending,
+        DownloadStatus::InProgress,
+        DownloadStatus::Ok,
+        DownloadStatus::Error,
+    ] {
+        assert_eq!(
+            status.to_string().parse::<DownloadStatus>().unwrap(),
+            status
+        );
+        assert_eq!(
+            serde_json::to_string(&status).unwrap(),
+            format!("\"{}\"", status)
+        );
+    }
+
+    assert!("Done".parse::<DownloadStatus>().is_err());
+}
+
+#[test]
+fn sync_request_unknown_download_status() {
+    let request = r#"{"item_metas": [{"id": "1f8f16fb-4a44-4d46-8de2-7b84e0b5c4a6", "item_id": "1f8f16fb-4a44-4d46-8de2-7b84e0b5c4a7", "new": false, "download_status": "Done", "playback_time": null, "play_count": 0, "update_ts": "2022-03-01T10:00:00+00:00"}]}"#;
+
+    assert!(serde_json::from_str::<SyncRequest>(request).is_err());
+    assert!(serde_json::from_str::<SyncRequest>(&request.replace("Done", "Pending")).is_ok());
+}
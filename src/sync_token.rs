@@ -0,0 +1,80 @@
+//! Opaque tokens marking a point in the history of changes.
+//!
+//! Every row records the id of the transaction, which changed it last. A token is the oldest
+//! transaction still running when the token was issued; all older transactions had finished
+//! by then, so their changes were visible to the reads following the issue of the token.
+//! Changes of the token's transaction and later ones are returned by the next request, even if
+//! they were committed late. Rows may therefore be returned twice, but none are missed.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset};
+use std::{fmt, str::FromStr};
+
+#[cfg(test)]
+mod test;
+
+const TOKEN_PREFIX: &str = "1.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SyncToken(i64);
+
+impl SyncToken {
+    pub fn from_transaction_id(id: i64) -> Self {
+        Self(id)
+    }
+
+    pub fn transaction_id(&self) -> i64 {
+        self.0
+    }
+}
+
+impl fmt::Display for SyncToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{:x}", TOKEN_PREFIX, self.0)
+    }
+}
+
+impl FromStr for SyncToken {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.strip_prefix(TOKEN_PREFIX)
+            .and_then(|id| i64::from_str_radix(id, 16).ok())
+            .filter(|id| *id >= 0)
+            .map(Self)
+            .ok_or_else(|| anyhow!("invalid sync token \"{}\"", s))
+    }
+}
+
+/// Restricts a list to the changes after a sync token or, less reliably, after a timestamp.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangeFilter {
+    pub since: Option<DateTime<FixedOffset>>,
+    pub token: Option<SyncToken>,
+}
+
+impl ChangeFilter {
+    pub fn from_query(since: Option<&str>, token: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            since: since.map(parse_since).transpose()?,
+            token: token.map(SyncToken::from_str).transpose()?,
+        })
+    }
+
+    /// The transaction id of the token as a query parameter.
+    pub fn transaction_id(&self) -> Option<i64> {
+        self.token.map(|t| t.transaction_id())
+    }
+}
+
+/// Parses a timestamp; the "+" of an offset, which was not encoded in the query, arrives as a space.
+fn parse_since(since: &str) -> Result<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(since)
+        .or_else(|e| match since.rsplit_once(' ') {
+            Some((time, offset)) => {
+                DateTime::parse_from_rfc3339(&format!("{}+{}", time, offset)).map_err(|_| e)
+            }
+            None => Err(e),
+        })
+        .map_err(|e| anyhow!("invalid timestamp \"{}\": {}", since, e))
+}
@@ -0,0 +1,55 @@
+use super::{ChangeFilter, SyncToken};
+use chrono::DateTime;
+use std::str::FromStr;
+
+#[test]
+fn token_round_trip() {
+    let token = SyncToken::from_transaction_id(123456);
+
+    assert_eq!(token.to_string(), "1.1e240");
+    assert_eq!(SyncToken::from_str(&token.to_string()).unwrap(), token);
+}
+
+#[test]
+fn invalid_tokens() {
+    assert!(SyncToken::from_str("").is_err());
+    assert!(SyncToken::from_str("1e240").is_err());
+    assert!(SyncToken::from_str("1.").is_err());
+    assert!(SyncToken::from_str("1.xyz").is_err());
+    assert!(SyncToken::from_str("2.1e240").is_err());
+    assert!(SyncToken::from_str("2022-03-01T10:00:00+00:00").is_err());
+}
+
+#[test]
+fn filter_from_query() {
+    assert_eq!(
+        ChangeFilter::from_query(None, None).unwrap(),
+        ChangeFilter::default()
+    );
+    assert_eq!(
+        ChangeFilter::from_query(Some("2022-03-01T10:00:00+00:00"), Some("1.ff")).unwrap(),
+        ChangeFilter {
+            since: Some(DateTime::parse_from_rfc3339("2022-03-01T10:00:00+00:00").unwrap()),
+            token: Some(SyncToken::from_transaction_id(255)),
+        }
+    );
+    assert_eq!(
+        ChangeFilter::from_query(None, Some("1.ff"))
+            .unwrap()
+            .transaction_id(),
+        Some(255)
+    );
+    assert!(ChangeFilter::from_query(Some("yesterday"), None).is_err());
+    assert!(ChangeFilter::from_query(None, Some("yesterday")).is_err());
+}
+
+#[test]
+fn filter_from_unencoded_offset() {
+    assert_eq!(
+        ChangeFilter::from_query(Some("2022-03-01T10:00:00 01:00"), None)
+            .unwrap()
+            .since,
+        Some(DateTime::parse_from_rfc3339("2022-03-01T10:00:00+01:00").unwrap())
+    );
+    assert!(ChangeFilter::from_query(Some("2022-03-01T10:00:00 yesterday"), None).is_err());
+}
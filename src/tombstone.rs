@@ -0,0 +1,43 @@
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+use std::{collections::HashMap, convert::TryFrom};
+use uuid::Uuid;
+
+#[cfg(test)]
+mod test;
+
+/// Record of a deleted feed, channel, or item.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Tombstone {
+    pub id: Uuid,
+    /// "feed", "channel", or "item"
+    pub object_type: String,
+    pub delete_ts: DateTime<FixedOffset>,
+}
+
+impl TryFrom<&tokio_postgres::Row> for Tombstone {
+    type Error = anyhow::Error;
+
+    fn try_from(row: &tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            object_type: row.try_get("object_type")?,
+            delete_ts: row.try_get("delete_ts")?,
+        })
+    }
+}
+
+/// The objects changed and deleted since a point in time.
+#[derive(Debug, Serialize)]
+pub struct Changes<T> {
+    /// token for the next request
+    pub token: String,
+    pub objects: Vec<T>,
+    pub tombstones: Vec<Tombstone>,
+}
+
+/// Whether the query of a list asks for the tombstones of deleted objects (`tombstones=true`).
+pub fn tombstones_requested(query: &HashMap<String, String>) -> bool {
+    query.get("tombstones").is_some_and(|t| t == "true")
+}
@@ -0,0 +1,49 @@
+use super::{tombstones_requested, Changes, Tombstone};
+use chrono::DateTime;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (String::from(*k), String::from(*v)))
+        .collect()
+}
+
+#[test]
+fn changes_serialization() {
+    let changes = Changes {
+        token: String::from("1.2a"),
+        objects: vec![1, 2],
+        tombstones: vec![Tombstone {
+            id: Uuid::nil(),
+            object_type: String::from("item"),
+            delete_ts: DateTime::parse_from_rfc3339("2022-03-01T10:00:00+01:00").unwrap(),
+        }],
+    };
+
+    assert_eq!(
+        serde_json::to_value(&changes).unwrap(),
+        serde_json::json!({
+            "token": "1.2a",
+            "objects": [1, 2],
+            "tombstones": [{
+                "id": "00000000-0000-0000-0000-000000000000",
+                "object_type": "item",
+                "delete_ts": "2022-03-01T10:00:00+01:00"
+            }]
+        })
+    );
+}
+
+#[test]
+fn tombstones_requested_by_query() {
+    assert!(tombstones_requested(&query(&[("tombstones", "true")])));
+    assert!(tombstones_requested(&query(&[
+        ("since", "2022-03-01T10:00:00Z"),
+        ("tombstones", "true")
+    ])));
+    assert!(!tombstones_requested(&query(&[("tombstones", "false")])));
+    assert!(!tombstones_requested(&query(&[("tombstones", "1")])));
+    assert!(!tombstones_requested(&query(&[])));
+}
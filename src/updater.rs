@@ -1,22 +1,45 @@
-use crate::{fetcher::request, repo::Repo, rss_feed::RssFeed};
+use crate::{
+    canonical_url::UrlCanonicalizer,
+    charset::decode_feed,
+    feed_health::{FeedFetchLog, HEALTH_HISTORY_LENGTH},
+    feed_schedule::{BackoffConfig, FeedSchedule},
+    fetcher::{permanent_redirect_target, Fetcher},
+    item::{enclosures_changed, ItemEnclosure},
+    repo::Repo,
+    rss_feed::{Diagnostic, ParseOptions, RssFeed},
+    sync_token::ChangeFilter,
+};
 use anyhow::Result;
 use chrono::Utc;
-use hyper::{Body, Method, Response};
+use hyper::{header::CONTENT_TYPE, Body, Method, Response};
 use log::{error, info, trace, warn};
 use podcast_player_common::{FeedUrl, FeedVal};
-use tokio::time::{sleep, Duration};
+use std::collections::HashMap;
+use tokio::time::{sleep, Duration, Instant};
 use uuid::Uuid;
 
-const TIMEOUT: Duration = Duration::from_secs(3);
-
 pub struct Updater {
     connection: String,
+    backoff: BackoffConfig,
+    fetcher: Fetcher,
+    parser: ParseOptions,
+    canonicalizer: UrlCanonicalizer,
 }
 
 impl Updater {
-    pub fn new(connection: &str) -> Self {
+    pub fn new(
+        connection: &str,
+        backoff: BackoffConfig,
+        fetcher: Fetcher,
+        parser: ParseOptions,
+        canonicalizer: UrlCanonicalizer,
+    ) -> Self {
         Self {
             connection: connection.into(),
+            backoff,
+            fetcher,
+            parser,
+            canonicalizer,
         }
     }
 
@@ -45,18 +68,88 @@ impl Updater {
 
         trace!("created repo");
 
-        let feeds = repo.get_objects::<FeedVal>(None).await?;
+        let feeds = repo
+            .get_objects::<FeedVal>(&ChangeFilter::default())
+            .await?;
 
         trace!("got {} feeds", feeds.len());
 
+        let mut schedules = repo
+            .get_feed_schedules()
+            .await?
+            .into_iter()
+            .map(|s| (s.feed_id, s))
+            .collect::<HashMap<Uuid, FeedSchedule>>();
+
         for feed in feeds {
             let title = feed.title.clone();
+            let mut schedule = schedules
+                .remove(&feed.id)
+                .unwrap_or_else(|| FeedSchedule::new(&feed.id));
+
+            if !schedule.is_due(&Utc::now().into()) {
+                trace!("skipping feed \"{}\" ({:?})", title, schedule);
+                continue;
+            }
 
             trace!("processing feed \"{}\"", title);
 
-            match process_feed(&feed, &repo).await {
-                Ok(_) => info!("successfully parsed \"{}\"", title),
-                Err(e) => error!("error parsing \"{}\": {}", title, e),
+            let start = Instant::now();
+            let mut fetch_log = FeedFetchLog::new(&feed.id);
+            let mut diagnostics = Vec::<Diagnostic>::new();
+
+            match process_feed(
+                &feed,
+                &repo,
+                &self.fetcher,
+                &self.parser,
+                &self.canonicalizer,
+                &mut fetch_log,
+                &mut diagnostics,
+            )
+            .await
+            {
+                Ok(_) => {
+                    info!("successfully parsed \"{}\"", title);
+                    schedule.record_success();
+                }
+                Err(e) => {
+                    error!("error parsing \"{}\": {}", title, e);
+                    fetch_log.error = Some(e.to_string());
+                    schedule.record_failure(&self.backoff, &Utc::now().into());
+
+                    if schedule.disabled {
+                        warn!(
+                            "disabling feed \"{}\" after {} consecutive failures",
+                            title, schedule.consecutive_failures
+                        );
+                    }
+                }
+            }
+
+            fetch_log.duration_ms = start.elapsed().as_millis() as i64;
+
+            match repo.create_feed_fetch_log(&fetch_log).await {
+                Ok(log) => {
+                    if let Err(e) = repo
+                        .create_feed_fetch_diagnostics(&log.id, &diagnostics)
+                        .await
+                    {
+                        error!("error recording diagnostics of \"{}\": {}", title, e);
+                    }
+                }
+                Err(e) => error!("error recording fetch of \"{}\": {}", title, e),
+            }
+
+            if let Err(e) = repo
+                .delete_old_feed_fetch_logs(&feed.id, HEALTH_HISTORY_LENGTH)
+                .await
+            {
+                error!("error deleting old fetches of \"{}\": {}", title, e);
+            }
+
+            if let Err(e) = repo.upsert_feed_schedule(&schedule).await {
+                error!("error updating schedule of \"{}\": {}", title, e);
             }
         }
 
@@ -64,16 +157,68 @@ impl Updater {
     }
 }
 
-async fn process_feed(db_feed: &FeedVal, repo: &Repo) -> Result<()> {
+async fn process_feed(
+    db_feed: &FeedVal,
+    repo: &Repo,
+    fetcher: &Fetcher,
+    parser: &ParseOptions,
+    canonicalizer: &UrlCanonicalizer,
+    fetch_log: &mut FeedFetchLog,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<()> {
     trace!("processing feed {:?}", db_feed);
 
-    let res = get_feed_response(db_feed, repo).await?;
+    let (feed_url_id, res) = get_feed_response(db_feed, repo, fetcher, fetch_log).await?;
 
     trace!("got feed response");
 
-    // Concatenate the body stream into a single buffer...
-    let buf = hyper::body::to_bytes(res).await?;
-    let rss_feed = RssFeed::try_from(std::str::from_utf8(&buf)?)?;
+    let content_type = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|c| c.to_str().ok())
+        .map(String::from);
+    let body = fetcher.read_body(res).await;
+
+    if let Err(e) = repo
+        .set_feed_url_error(&feed_url_id, body.as_ref().err().map(error_text).as_deref())
+        .await
+    {
+        error!("error recording error of \"{}\": {}", db_feed.title, e);
+    }
+
+    let buf = body?;
+    let (mut rss_feed, recovery_report) =
+        RssFeed::parse_lenient(&*decode_feed(&buf, content_type.as_deref())?, parser)?;
+
+    if !recovery_report.is_empty() {
+        warn!(
+            "repaired malformed feed \"{}\": {:?}",
+            db_feed.title, recovery_report
+        );
+    }
+
+    if !rss_feed.diagnostics.is_empty() {
+        info!(
+            "found {} problems in feed \"{}\"",
+            rss_feed.diagnostics.len(),
+            db_feed.title
+        );
+    }
+
+    diagnostics.append(&mut rss_feed.diagnostics);
+    fetch_log.channel_count = rss_feed.channels.len() as i32;
+    fetch_log.item_count = rss_feed.channels.iter().map(|c| c.items.len() as i32).sum();
+
+    if let Some(new_feed_url) = &rss_feed.new_feed_url {
+        if let Err(e) =
+            promote_new_feed_url(db_feed, repo, fetcher, parser, &feed_url_id, new_feed_url).await
+        {
+            warn!(
+                "error promoting new feed url \"{}\" of \"{}\": {}",
+                new_feed_url, db_feed.title, e
+            );
+        }
+    }
 
     for rss_channel in &rss_feed.channels {
         let db_channel = match repo
@@ -103,32 +248,90 @@ async fn process_feed(db_feed: &FeedVal, repo: &Repo) -> Result<()> {
             }
         };
 
-        for rss_item in &rss_channel.items {
-            match repo
-                .get_item_by_title_date_channel_id(&*rss_item.title, &rss_item.date, &db_channel.id)
-                .await?
-            {
-                Some(mut i) => {
-                    let enclosure_type = rss_item.enclosure.mime_type.clone();
-                    let enclosure_url = rss_item.enclosure.url.clone();
+        if let Err(e) = repo
+            .update_channel_metadata(&db_channel.id, &rss_channel.metadata)
+            .await
+        {
+            error!(
+                "error updating metadata of channel \"{}\": {}",
+                rss_channel.title, e
+            );
+        }
+
+        // items without date get the time they were first seen, going back in feed order
+        let first_seen = Utc::now();
+
+        for (index, rss_item) in rss_channel.items.iter().enumerate() {
+            let db_item = match &rss_item.date {
+                Some(date) => match repo
+                    .get_item_by_title_date_channel_id(&*rss_item.title, date, &db_channel.id)
+                    .await?
+                {
+                    Some(i) => Some(i),
+                    None => {
+                        let estimated = repo
+                            .get_estimated_item_by_title_channel_id(
+                                &*rss_item.title,
+                                &db_channel.id,
+                            )
+                            .await?;
+
+                        if let Some(i) = &estimated {
+                            repo.set_item_date(&i.id, date).await?;
+                        }
+
+                        estimated
+                    }
+                },
+                None => {
+                    repo.get_estimated_item_by_title_channel_id(&*rss_item.title, &db_channel.id)
+                        .await?
+                }
+            };
+
+            let item_id = match (db_item, &rss_item.enclosure) {
+                (Some(mut i), Some(enclosure)) => {
+                    let enclosure_type = enclosure.mime_type.clone();
+                    let enclosure_url = enclosure.url.clone();
 
                     if i.needs_update(&enclosure_type, &enclosure_url) {
                         i.enclosure_type = enclosure_type;
                         i.enclosure_url = enclosure_url;
+                        i.size = enclosure.length;
 
                         repo.update_item(&i).await?;
                     }
+
+                    i.id
                 }
-                None => {
-                    repo.create_item(
-                        &*rss_item.title,
-                        &rss_item.date,
-                        &*rss_item.enclosure.mime_type,
-                        &*rss_item.enclosure.url,
-                        &db_channel.id,
-                        rss_item.enclosure.length,
-                    )
-                    .await?;
+                (Some(i), None) => i.id,
+                (None, _) => {
+                    let date = rss_item.date.unwrap_or_else(|| {
+                        (first_seen - chrono::Duration::seconds(index as i64)).into()
+                    });
+
+                    repo.create_item(rss_item, &date, &db_channel.id).await?.id
+                }
+            };
+
+            repo.update_item_image(&item_id, rss_item.image.as_deref())
+                .await?;
+
+            if let Some(enclosure) = &rss_item.enclosure {
+                repo.update_item_canonical_enclosure_url(
+                    &item_id,
+                    canonicalizer.canonicalize(&enclosure.url).as_deref(),
+                )
+                .await?;
+
+                // keep the variants of items, whose enclosure vanished, like the item itself
+                let enclosures = ItemEnclosure::from_rss_item(&item_id, rss_item, canonicalizer);
+
+                if enclosures_changed(
+                    &repo.get_item_enclosures_by_item_id(&item_id).await?,
+                    &enclosures,
+                ) {
+                    repo.replace_item_enclosures(&item_id, &enclosures).await?;
                 }
             }
         }
@@ -137,7 +340,12 @@ async fn process_feed(db_feed: &FeedVal, repo: &Repo) -> Result<()> {
     Ok(())
 }
 
-async fn get_feed_response(db_feed: &FeedVal, repo: &Repo) -> Result<Response<Body>> {
+async fn get_feed_response(
+    db_feed: &FeedVal,
+    repo: &Repo,
+    fetcher: &Fetcher,
+    fetch_log: &mut FeedFetchLog,
+) -> Result<(Uuid, Response<Body>)> {
     trace!("getting feed urls by feed id");
 
     let mut feed_urls = repo.get_urls_by_feed_id(&db_feed.id).await?;
@@ -146,13 +354,49 @@ async fn get_feed_response(db_feed: &FeedVal, repo: &Repo) -> Result<Response<Bo
 
     feed_urls.sort();
 
+    // try the preferred url first
+    let preferred_id = repo.get_preferred_url_id_by_feed_id(&db_feed.id).await?;
+
+    feed_urls.sort_by_key(|fu| Some(fu.id) != preferred_id);
+
     let mut feed_url_ids = feed_urls.iter().map(|fu| fu.id).collect::<Vec<Uuid>>();
 
     while feed_url_ids.len() > 0 {
         // find first url, which has not been tried
         match feed_urls.iter().find(|&fu| feed_url_ids.contains(&fu.id)) {
             Some(feed_url) => {
-                let res = request(&feed_url.url, &TIMEOUT, &Method::GET).await?;
+                fetch_log.url = Some(feed_url.url.clone());
+                fetch_log.status = None;
+
+                let res = match fetcher
+                    .request_compressed(&feed_url.url, &Method::GET)
+                    .await
+                {
+                    Ok(res) => res,
+                    Err(e) => {
+                        // an unreachable or malformed url must not keep the other urls from being tried
+                        warn!(
+                            "error requesting url \"{}\" of feed \"{}\": {}",
+                            feed_url.url, db_feed.title, e
+                        );
+                        if let Err(e) = repo
+                            .set_feed_url_error(&feed_url.id, Some(&*error_text(&e)))
+                            .await
+                        {
+                            error!("error recording error of url \"{}\": {}", feed_url.url, e);
+                        }
+                        feed_url_ids.retain(|&id| id != feed_url.id);
+                        continue;
+                    }
+                };
+
+                if let Some((res_url, res_status)) = res.1.last() {
+                    fetch_log.url = Some(res_url.clone());
+                    fetch_log.status = Some(*res_status);
+                }
+
+                let redirect_target = permanent_redirect_target(&res.1);
+                let mut chain_ids = Vec::<Uuid>::new();
 
                 for (res_url, res_status) in res.1 {
                     // check whether the url is in the repo
@@ -166,25 +410,36 @@ async fn get_feed_response(db_feed: &FeedVal, repo: &Repo) -> Result<Response<Bo
                             new_fu.status = Some(res_status);
                             new_fu.update_ts = Utc::now().into();
                             repo.update_feed_url(&new_fu).await?;
+                            chain_ids.push(fu.id);
                         }
                         None => {
                             // add if not
-                            repo.create_feed_url(&FeedUrl {
-                                feed_id: db_feed.id,
-                                id: Uuid::new_v4(),
-                                manual: false,
-                                status: Some(res_status),
-                                synced: false,
-                                update_ts: Utc::now().into(),
-                                url: res_url,
-                            })
-                            .await?;
+                            let new_fu = repo
+                                .create_feed_url(&FeedUrl {
+                                    feed_id: db_feed.id,
+                                    id: Uuid::new_v4(),
+                                    manual: false,
+                                    status: Some(res_status),
+                                    synced: false,
+                                    update_ts: Utc::now().into(),
+                                    url: res_url,
+                                })
+                                .await?;
+
+                            chain_ids.push(new_fu.id);
                         }
                     }
                 }
 
-                if let Some(resp) = res.0 {
-                    return Ok(resp);
+                // make a permanent redirect target the preferred url for future requests
+                if let Some(target) = redirect_target {
+                    info!("feed \"{}\" moved permanently", db_feed.title);
+                    repo.promote_feed_url(&db_feed.id, &chain_ids[0], &chain_ids[target])
+                        .await?;
+                }
+
+                if let (Some(resp), Some(id)) = (res.0, chain_ids.last()) {
+                    return Ok((*id, resp));
                 }
             }
             None => {
@@ -201,3 +456,77 @@ async fn get_feed_response(db_feed: &FeedVal, repo: &Repo) -> Result<Response<Bo
         "none of the urls for the feed was retrievable"
     ))
 }
+
+async fn promote_new_feed_url(
+    db_feed: &FeedVal,
+    repo: &Repo,
+    fetcher: &Fetcher,
+    parser: &ParseOptions,
+    feed_url_id: &Uuid,
+    new_feed_url: &str,
+) -> Result<()> {
+    let feed_urls = repo.get_urls_by_feed_id(&db_feed.id).await?;
+    let new_id = match feed_urls.iter().find(|fu| fu.url == new_feed_url) {
+        Some(fu) => fu.id,
+        None => {
+            repo.create_feed_url(&FeedUrl {
+                feed_id: db_feed.id,
+                id: Uuid::new_v4(),
+                manual: false,
+                status: None,
+                synced: false,
+                update_ts: Utc::now().into(),
+                url: new_feed_url.into(),
+            })
+            .await?
+            .id
+        }
+    };
+
+    if new_id == *feed_url_id
+        || repo.get_preferred_url_id_by_feed_id(&db_feed.id).await? == Some(new_id)
+    {
+        return Ok(());
+    }
+
+    // only follow the announcement once the new url has delivered a feed
+    if let Err(e) = check_feed_url(fetcher, parser, new_feed_url).await {
+        repo.set_feed_url_error(&new_id, Some(&*error_text(&e)))
+            .await?;
+        return Err(e);
+    }
+
+    info!(
+        "feed \"{}\" announced new feed url \"{}\"",
+        db_feed.title, new_feed_url
+    );
+    repo.set_feed_url_error(&new_id, None).await?;
+    repo.promote_feed_url(&db_feed.id, feed_url_id, &new_id)
+        .await?;
+
+    Ok(())
+}
+
+async fn check_feed_url(fetcher: &Fetcher, parser: &ParseOptions, url: &str) -> Result<()> {
+    let res = match fetcher.request_compressed(url, &Method::GET).await?.0 {
+        Some(res) => res,
+        None => return Err(anyhow::anyhow!("no feed retrievable from \"{}\"", url)),
+    };
+    let content_type = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|c| c.to_str().ok())
+        .map(String::from);
+    let buf = fetcher.read_body(res).await?;
+    let (rss_feed, _) =
+        RssFeed::parse_lenient(&decode_feed(&buf, content_type.as_deref())?, parser)?;
+
+    match rss_feed.channels.len() {
+        0 => Err(anyhow::anyhow!("no channels found at \"{}\"", url)),
+        _ => Ok(()),
+    }
+}
+
+fn error_text(e: &anyhow::Error) -> String {
+    e.to_string().chars().take(1024).collect()
+}
@@ -0,0 +1,88 @@
+use crate::{
+    charset::decode_feed,
+    fetcher::Fetcher,
+    rss_feed::{Diagnostic, ParseOptions, RecoveryReport, RssChannel, RssFeed},
+};
+use anyhow::{anyhow, Result};
+use hyper::{header::CONTENT_TYPE, Method};
+use serde::Serialize;
+
+#[cfg(test)]
+mod test;
+
+/// The result of parsing a feed without storing it.
+#[derive(Debug, Default, Serialize)]
+pub struct Validation {
+    pub valid: bool,
+    pub error: Option<String>,
+    /// urls requested with their status, including redirects
+    pub urls: Vec<(String, i16)>,
+    pub new_feed_url: Option<String>,
+    pub recovery: RecoveryReport,
+    pub diagnostics: Vec<Diagnostic>,
+    pub channels: Vec<RssChannel>,
+}
+
+impl Validation {
+    pub async fn from_url(fetcher: &Fetcher, url: &str, options: &ParseOptions) -> Self {
+        let mut validation = Self::default();
+        let result = validation.fetch(fetcher, url, options).await;
+
+        validation.finish(result)
+    }
+
+    pub fn from_body(body: &[u8], content_type: Option<&str>, options: &ParseOptions) -> Self {
+        let mut validation = Self::default();
+        let result = validation.parse(body, content_type, options);
+
+        validation.finish(result)
+    }
+
+    async fn fetch(&mut self, fetcher: &Fetcher, url: &str, options: &ParseOptions) -> Result<()> {
+        let (res, urls) = fetcher.request_compressed(url, &Method::GET).await?;
+
+        self.urls = urls;
+
+        let res = res.ok_or_else(|| match self.urls.last() {
+            Some((url, status)) => anyhow!("request to \"{}\" returned status {}", url, status),
+            None => anyhow!("no response"),
+        })?;
+        let content_type = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|c| c.to_str().ok())
+            .map(String::from);
+        let body = fetcher.read_body(res).await?;
+
+        self.parse(&body, content_type.as_deref(), options)
+    }
+
+    fn parse(
+        &mut self,
+        body: &[u8],
+        content_type: Option<&str>,
+        options: &ParseOptions,
+    ) -> Result<()> {
+        let (feed, recovery) = RssFeed::parse_lenient(&*decode_feed(body, content_type)?, options)?;
+
+        self.new_feed_url = feed.new_feed_url;
+        self.recovery = recovery;
+        self.diagnostics = feed.diagnostics;
+        self.channels = feed.channels;
+
+        if self.channels.is_empty() {
+            return Err(anyhow!("no channel found"));
+        }
+
+        Ok(())
+    }
+
+    fn finish(mut self, result: Result<()>) -> Self {
+        match result {
+            Ok(_) => self.valid = true,
+            Err(e) => self.error = Some(e.to_string()),
+        }
+
+        self
+    }
+}
@@ -0,0 +1,52 @@
+use super::Validation;
+use crate::rss_feed::ParseOptions;
+use std::fs;
+
+fn validate(file: &str) -> Validation {
+    Validation::from_body(
+        &fs::read(file).unwrap(),
+        Some("application/rss+xml"),
+        &ParseOptions::default(),
+    )
+}
+
+#[test]
+fn validate_valid_feed() {
+    let validation = validate("testFiles/bots.xml");
+
+    assert!(validation.valid);
+    assert_eq!(validation.error, None);
+    assert_eq!(validation.channels[0].items.len(), 2);
+    assert!(validation.recovery.is_empty());
+    assert!(validation.diagnostics.is_empty());
+}
+
+#[test]
+fn validate_repaired_feed() {
+    let validation = validate("testFiles/broken_ampersand.xml");
+
+    assert!(validation.valid);
+    assert_eq!(validation.recovery.ampersands_escaped, 5);
+    assert!(!validation.diagnostics.is_empty());
+}
+
+#[test]
+fn validate_invalid_feed() {
+    let validation = Validation::from_body(b"<html><body>", None, &ParseOptions::default());
+
+    assert!(!validation.valid);
+    assert!(validation.error.is_some());
+    assert!(validation.channels.is_empty());
+}
+
+#[test]
+fn validate_feed_without_channel() {
+    let validation = Validation::from_body(
+        b"<rss version=\"2.0\"></rss>",
+        None,
+        &ParseOptions::default(),
+    );
+
+    assert!(!validation.valid);
+    assert_eq!(validation.error.as_deref(), Some("no channel found"));
+}